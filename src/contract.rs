@@ -1,17 +1,68 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    to_binary, to_vec, Addr, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, MessageInfo,
+    Order, Reply, Response, StdResult, Storage, SubMsg, SubMsgResult, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
+#[cfg(feature = "executable")]
+use cosmwasm_std::{Empty, QueryRequest, WasmQuery};
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::{Bound, Map};
+use sha2::{Digest, Sha256};
 
 use crate::error::ContractError;
-use crate::msg::{ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{State, STATE};
+#[cfg(feature = "transferable")]
+use crate::msg::CreatorTransferHookMsg;
+#[cfg(feature = "burnable")]
+use crate::msg::UnwrapExecuteMsg;
+use crate::msg::{
+    ApprovalsResponse, BalancesResponse, BookKeyResponse, BuildInfoResponse, ConfigResponse,
+    ConfigV2Response, CreatedAtResponse, CurrentCounterOfferResponse, DenomPairResponse,
+    ExampleMessagesResponse, ExecuteMsg, Expiry, FinalActionResponse, InstantiateMsg,
+    LifePercentResponse, MigrateMsg, MigrationHistoryResponse, NearExpiryResponse, NftInfoResponse,
+    OraclePriceResponse, OracleQueryMsg, OriginalTermsResponse, PaymentProgressResponse,
+    ProceedsResponse, QueryMsg, RefundResponse, RegistryExecuteMsg, RequiredPaymentResponse,
+    SolventResponse, TimeOracleQueryMsg, TimeOracleResponse, Trait, TransferHistoryResponse,
+    TransitionsResponse, WouldAcceptResponse, CONFIG_SCHEMA_VERSION,
+};
+#[cfg(feature = "executable")]
+use crate::msg::{CreatorCallbackMsg, DistributionExecuteMsg, SwapExecuteMsg, VestingExecuteMsg};
+#[cfg(feature = "executable")]
+use crate::state::Escrow;
+#[cfg(any(feature = "burnable", feature = "executable"))]
+use crate::state::PendingDistribution;
+#[cfg(feature = "transferable")]
+use crate::state::TransferRecord;
+use crate::state::{
+    Approval, DustPolicy, FeeConfig, MigrationRecord, OptionKind, OracleTime, OriginalTerms, State,
+    APPROVALS, DENOM_ALIASES, DENOM_DISPLAY, ESCROW, EXECUTOR_REWARDS, FEES_ACCRUED, FEE_CONFIG,
+    INSTALLMENTS, MIGRATIONS, ORACLE_TIME, ORIGINAL_TERMS, PENDING_COLLATERAL,
+    PENDING_DISTRIBUTION, PENDING_UNWRAP, PENDING_VESTING, PRIVATE, STATE, TRANSFER_HISTORY,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:simple-option";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Source revision baked in at compile time, e.g. `GIT_HASH=$(git rev-parse HEAD) cargo wasm`
+const GIT_HASH: Option<&str> = option_env!("GIT_HASH");
+
+const MAX_METADATA_URI_LEN: usize = 512;
+const MAX_BPS: u16 = 10_000;
+
+const CREATOR_CALLBACK_REPLY_ID: u64 = 1;
+const DISTRIBUTION_REPLY_ID: u64 = 2;
+const CREATOR_TRANSFER_HOOK_REPLY_ID: u64 = 3;
+const CREATOR_PAID_REPLY_ID: u64 = 4;
+const UNWRAP_REPLY_ID: u64 = 5;
+const VESTING_REPLY_ID: u64 = 6;
+
+// page sizes for paginated queries
+const MAX_LIMIT: u32 = 100;
+const DEFAULT_LIMIT: u32 = 30;
+#[cfg(feature = "transferable")]
+const DEFAULT_HISTORY_LEN: u32 = 10;
+/// Stands in for the creator and owner of a private option
+const REDACTED: &str = "redacted";
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -20,20 +71,309 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    if msg.expires <= _env.block.height {
-        return Err(ContractError::Expired {});
+    // under a time oracle every expiry check, including this one, uses the time it reports
+    let now = match &msg.time_oracle {
+        Some(oracle) => Some(query_oracle_time(deps.as_ref(), oracle)?),
+        None => None,
+    };
+    validate_instantiate(&msg, now.unwrap_or(_env.block.height))?;
+    // collateral denoms cannot change afterwards: `AddCollateral` only tops up existing ones
+    if exceeds_denom_cap(&info.funds, msg.max_collateral_denoms) {
+        return Err(ContractError::TooManyDenoms {});
+    }
+    if msg.reject_at_the_money
+        && std::iter::once(&msg.counter_offer)
+            .chain(&msg.alternative_counter_offers)
+            .any(|counter_offer| is_at_the_money(&info.funds, counter_offer))
+    {
+        return Err(ContractError::AtTheMoneyNotAllowed {});
     }
+    if let Some(oracle) = &msg.oracle {
+        // an alternative settles the option just like the primary counter offer
+        for counter_offer in
+            std::iter::once(&msg.counter_offer).chain(&msg.alternative_counter_offers)
+        {
+            check_oracle_price(
+                deps.as_ref(),
+                oracle,
+                &info.funds,
+                counter_offer,
+                msg.max_mispricing_bps,
+            )?;
+        }
+    }
+    let expires = resolve_expiry(&msg, now.unwrap_or(_env.block.height))?;
     let state = State {
         creator: info.sender.clone(),
         owner: info.sender.clone(),
         collateral: info.funds,
         counter_offer: msg.counter_offer,
-        expires: msg.expires,
+        expires,
+        option_type: msg.option_type,
+        metadata_uri: msg.metadata_uri,
+        early_exercise_penalty_bps: msg.early_exercise_penalty_bps,
+        penalty_until_height: msg.penalty_until_height,
+        pending_creator: None,
+        created_height: _env.block.height,
+        min_life_blocks: msg.min_life_blocks,
+        admin: msg.admin,
+        frozen_denoms: vec![],
+        burn_fee_bps: msg.burn_fee_bps,
+        burn_fee_recipient: msg.burn_fee_recipient,
+        burn_actual_balance: msg.burn_actual_balance,
+        creator_callback: msg.creator_callback,
+        alternative_counter_offers: msg.alternative_counter_offers,
+        recovery_address: msg.recovery_address,
+        recovery_after_blocks: msg.recovery_after_blocks,
+        topup_extends_expiry: msg.topup_extends_expiry,
+        extension_per_topup_blocks: msg.extension_per_topup_blocks,
+        paid_so_far: vec![],
+        distribution_contract: msg.distribution_contract,
+        dust_recipient: msg.dust_recipient,
+        soulbound: false,
+        warning_window_blocks: msg.warning_window_blocks,
+        execution_condition: msg.execution_condition,
+        min_refund_threshold: msg.min_refund_threshold,
+        swap_router: msg.swap_router,
+        desired_proceeds_denom: msg.desired_proceeds_denom,
+        min_swap_output: msg.min_swap_output,
+        lapse_to_creator: msg.lapse_to_creator,
+        inclusive_expiry: msg.inclusive_expiry,
+        co_owner: None,
+        co_owner_split_bps: 0,
+        creator_transfer_hook: msg.creator_transfer_hook,
+        burn_confirmation_blocks: msg.burn_confirmation_blocks,
+        two_phase_settlement: msg.two_phase_settlement,
+        unwrap_on_burn: msg.unwrap_on_burn,
+        accepted_denoms: msg.accepted_denoms,
+        time_oracle: msg.time_oracle,
+        history_len: msg.history_len,
+        private: msg.private,
+        vesting_contract: msg.vesting_contract,
+        created_time: now,
+        settlement_denom: msg.settlement_denom,
+        lot_size: msg.lot_size,
+        max_counter_offer_denoms: msg.max_counter_offer_denoms,
+        reject_at_the_money: msg.reject_at_the_money,
+        oracle: msg.oracle,
+        max_mispricing_bps: msg.max_mispricing_bps,
+        counter_offer_revision: 0,
     };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     STATE.save(deps.storage, &state)?;
+    if state.private {
+        PRIVATE.save(deps.storage, &true)?;
+    }
+    ORIGINAL_TERMS.save(
+        deps.storage,
+        &OriginalTerms {
+            creator: state.creator.clone(),
+            collateral: state.collateral.clone(),
+            counter_offer: state.counter_offer.clone(),
+            expires: state.expires,
+            created_height: state.created_height,
+        },
+    )?;
+    for alias in msg.denom_aliases {
+        DENOM_ALIASES.save(deps.storage, &alias.alias, &alias.canonical)?;
+    }
+    for display in msg.denom_display {
+        DENOM_DISPLAY.save(deps.storage, &display.denom, &display.display)?;
+    }
+    if let Some(admin) = msg.fee_admin {
+        FEE_CONFIG.save(
+            deps.storage,
+            &FeeConfig {
+                admin,
+                execute_fee_bps: msg.execute_fee_bps,
+            },
+        )?;
+    }
+
+    let mut res = Response::new();
+    if let Some(registry) = msg.registry {
+        // a plain submessage: if the registry rejects the option, instantiation fails too
+        res = res.add_submessage(SubMsg::new(WasmMsg::Execute {
+            contract_addr: registry.to_string(),
+            msg: to_binary(&RegistryExecuteMsg::RegisterOption {
+                creator: state.creator.clone(),
+                counter_offer: state.counter_offer.clone(),
+                expires: state.expires,
+            })?,
+            funds: vec![],
+        }));
+    }
+
+    Ok(res
+        .add_attribute("action", "instantiate")
+        .add_attribute("option_type", state.option_type.to_string())
+        .add_attribute("method", "instantiate"))
+}
+
+/// Absolute expiry height for `msg` when instantiated at `height`.
+pub fn resolve_expiry(msg: &InstantiateMsg, height: u64) -> Result<u64, ContractError> {
+    match msg.expiry() {
+        Some(Expiry::AtHeight(expires)) => Ok(expires),
+        Some(Expiry::InBlocks(blocks)) => {
+            height.checked_add(blocks).ok_or(ContractError::Overflow {})
+        }
+        None => Err(ContractError::AmbiguousExpiry {}),
+    }
+}
+
+/// Runs every instantiate-time check against `msg` as if it were submitted at `height`, or
+/// at time oracle reading `height` when `time_oracle` is set.
+/// Library consumers can call this to pre-validate a message before deploying.
+pub fn validate_instantiate(msg: &InstantiateMsg, height: u64) -> Result<(), ContractError> {
+    if msg.time_oracle.is_some()
+        && (msg.expires_in_blocks.is_some()
+            || msg.warning_window_blocks > 0
+            || msg.recovery_after_blocks > 0
+            || msg.burn_confirmation_blocks > 0
+            || msg.extension_per_topup_blocks > 0)
+    {
+        return Err(ContractError::InvalidTimeOracleConfig {});
+    }
+    if resolve_expiry(msg, height)? <= height {
+        return Err(ContractError::Expired {});
+    }
+    if let OptionKind::Other(kind) = &msg.option_type {
+        if kind.trim().is_empty() {
+            return Err(ContractError::InvalidOptionType {});
+        }
+    }
+    if let Some(uri) = &msg.metadata_uri {
+        validate_metadata_uri(uri)?;
+    }
+    if msg.early_exercise_penalty_bps > MAX_BPS
+        || msg.burn_fee_bps > MAX_BPS
+        || msg.execute_fee_bps > MAX_BPS
+        || msg.max_mispricing_bps > MAX_BPS
+    {
+        return Err(ContractError::InvalidBasisPoints {});
+    }
+    if (msg.burn_fee_bps > 0 && msg.burn_fee_recipient.is_none())
+        || (msg.execute_fee_bps > 0 && msg.fee_admin.is_none())
+    {
+        return Err(ContractError::MissingFeeRecipient {});
+    }
+    if msg.swap_router.is_some()
+        && (msg.desired_proceeds_denom.is_none() || msg.distribution_contract.is_some())
+    {
+        return Err(ContractError::InvalidSwapConfig {});
+    }
+    if msg.two_phase_settlement
+        && (msg.distribution_contract.is_some() || msg.swap_router.is_some())
+    {
+        return Err(ContractError::InvalidTwoPhaseSettlement {});
+    }
+    if msg.vesting_contract.is_some()
+        && (msg.distribution_contract.is_some()
+            || msg.swap_router.is_some()
+            || msg.two_phase_settlement)
+    {
+        return Err(ContractError::InvalidVestingConfig {});
+    }
+    for counter_offer in std::iter::once(&msg.counter_offer).chain(&msg.alternative_counter_offers)
+    {
+        check_counter_offer_limits(
+            counter_offer,
+            msg.max_counter_offer_denoms,
+            msg.settlement_denom.as_ref(),
+            msg.lot_size.as_ref(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The shape checks every counter offer must pass, at instantiate and again on relist.
+fn check_counter_offer_limits(
+    counter_offer: &[Coin],
+    max_denoms: Option<u32>,
+    settlement_denom: Option<&String>,
+    lot_size: Option<&Coin>,
+) -> Result<(), ContractError> {
+    validate_counter_offer(counter_offer)?;
+    if exceeds_denom_cap(counter_offer, max_denoms) {
+        return Err(ContractError::TooManyDenoms {});
+    }
+    if let Some(denom) = settlement_denom {
+        if counter_offer.len() != 1 || &counter_offer[0].denom != denom {
+            return Err(ContractError::InvalidSettlementDenom {});
+        }
+    }
+    if let Some(lot) = lot_size {
+        let off_lot = counter_offer.iter().any(|c| {
+            c.denom == lot.denom
+                && (lot.amount.is_zero() || c.amount.u128() % lot.amount.u128() != 0)
+        });
+        if off_lot {
+            return Err(ContractError::NotLotMultiple {});
+        }
+    }
+    Ok(())
+}
+
+/// A single collateral coin priced at a single counter offer coin of the same amount.
+fn is_at_the_money(collateral: &[Coin], counter_offer: &[Coin]) -> bool {
+    matches!((collateral, counter_offer), ([c], [o]) if c.amount == o.amount)
+}
+
+fn exceeds_denom_cap(coins: &[Coin], max: Option<u32>) -> bool {
+    matches!(max, Some(max) if coins.len() > max as usize)
+}
+
+/// Rejects a single-coin counter offer that is more than `max_mispricing_bps` away from the
+/// oracle's fair price for `collateral`.
+fn check_oracle_price(
+    deps: Deps,
+    oracle: &Addr,
+    collateral: &[Coin],
+    counter_offer: &[Coin],
+    max_mispricing_bps: u16,
+) -> Result<(), ContractError> {
+    let offer = match counter_offer {
+        [offer] => offer,
+        _ => return Err(ContractError::Mispriced {}),
+    };
+    let fair: OraclePriceResponse = deps.querier.query_wasm_smart(
+        oracle,
+        &OracleQueryMsg::Price {
+            collateral: collateral.to_vec(),
+            denom: offer.denom.clone(),
+        },
+    )?;
+    let distance = if offer.amount > fair.amount {
+        offer.amount - fair.amount
+    } else {
+        fair.amount - offer.amount
+    };
+    if distance > fair.amount.multiply_ratio(max_mispricing_bps, MAX_BPS) {
+        return Err(ContractError::Mispriced {});
+    }
+    Ok(())
+}
+
+fn validate_counter_offer(counter_offer: &[Coin]) -> Result<(), ContractError> {
+    if counter_offer.is_empty() || counter_offer.iter().any(|c| c.amount.is_zero()) {
+        return Err(ContractError::EmptyCounterOffer {});
+    }
+    for (i, coin) in counter_offer.iter().enumerate() {
+        if counter_offer[..i].iter().any(|c| c.denom == coin.denom) {
+            return Err(ContractError::DuplicateDenom {
+                denom: coin.denom.clone(),
+            });
+        }
+    }
+    Ok(())
+}
 
-    Ok(Response::default())
+fn validate_metadata_uri(uri: &str) -> Result<(), ContractError> {
+    if uri.trim().is_empty() || uri.chars().count() > MAX_METADATA_URI_LEN {
+        return Err(ContractError::InvalidMetadataUri {});
+    }
+    Ok(())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -44,290 +384,5918 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Transfer { recipient } => try_transfer(deps, info, recipient),
+        #[cfg(feature = "transferable")]
+        ExecuteMsg::Transfer { recipient } => try_transfer(deps, _env, info, recipient),
+        #[cfg(feature = "executable")]
         ExecuteMsg::Execute {} => try_execute(deps, _env, info),
+        #[cfg(feature = "burnable")]
         ExecuteMsg::Burn {} => try_burn(deps, _env, info),
+        #[cfg(feature = "burnable")]
+        ExecuteMsg::BurnAvailable {} => try_burn_available(deps, _env, info),
+        ExecuteMsg::AddCollateral {} => try_add_collateral(deps, _env, info),
+        #[cfg(feature = "executable")]
+        ExecuteMsg::AddExecutorReward {} => try_add_executor_reward(deps, _env, info),
+        ExecuteMsg::SetMetadataUri { uri } => try_set_metadata_uri(deps, info, uri),
+        ExecuteMsg::ProposeCreatorChange { new_creator } => {
+            try_propose_creator_change(deps, info, new_creator)
+        }
+        ExecuteMsg::AcceptCreatorChange {} => try_accept_creator_change(deps, info),
+        ExecuteMsg::SetFrozenDenoms { denoms } => try_set_frozen_denoms(deps, info, denoms),
+        ExecuteMsg::SetAcceptedDenoms { denoms } => try_set_accepted_denoms(deps, info, denoms),
+        ExecuteMsg::AbortDueToFrozenDenom { denom } => {
+            try_abort_due_to_frozen_denom(deps, info, denom)
+        }
+        ExecuteMsg::UpdateAdmin { new_admin } => try_update_admin(deps, info, new_admin),
+        ExecuteMsg::Recover {} => try_recover(deps, _env),
+        ExecuteMsg::Soulbind {} => try_soulbind(deps, info),
+        ExecuteMsg::Relist {
+            new_counter_offer,
+            new_expires,
+        } => try_relist(deps, _env, info, new_counter_offer, new_expires),
+        ExecuteMsg::SetCoOwner {
+            co_owner,
+            split_bps,
+        } => try_set_co_owner(deps, info, co_owner, split_bps),
+        ExecuteMsg::Approve { operator, expires } => try_approve(deps, info, operator, expires),
+        ExecuteMsg::Revoke { operator } => try_revoke(deps, info, operator),
+        ExecuteMsg::WithdrawFees { recipient } => try_withdraw_fees(deps, info, recipient),
+        #[cfg(feature = "executable")]
+        ExecuteMsg::EscrowCounterOffer {} => try_escrow_counter_offer(deps, _env, info),
+        #[cfg(feature = "executable")]
+        ExecuteMsg::PayInstallment {} => try_pay_installment(deps, _env, info),
     }
 }
 
+/// Loads the option, failing with `OptionSettled` once it has been executed, burned or recovered.
+fn load_state(storage: &dyn Storage) -> Result<State, ContractError> {
+    STATE
+        .may_load(storage)?
+        .ok_or(ContractError::OptionSettled {})
+}
+
+/// `STATE.update` with the `OptionSettled` error of `load_state`.
+fn update_state<F>(storage: &mut dyn Storage, action: F) -> Result<State, ContractError>
+where
+    F: FnOnce(State) -> Result<State, ContractError>,
+{
+    let state = action(load_state(storage)?)?;
+    STATE.save(storage, &state)?;
+    Ok(state)
+}
+
+#[cfg(feature = "transferable")]
 pub fn try_transfer(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     recipient: Addr,
 ) -> Result<Response, ContractError> {
-    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
-        if info.sender != state.owner {
-            return Err(ContractError::Unauthorized {});
-        }
-        state.owner = recipient.clone();
-        Ok(state)
-    })?;
+    let mut state = load_state(deps.storage)?;
+    if !can_act_for_owner(deps.storage, &state, &env, &info.sender)? {
+        return Err(ContractError::NotOwner {});
+    }
+    if state.soulbound {
+        return Err(ContractError::Soulbound {});
+    }
+    let prev_owner = std::mem::replace(&mut state.owner, recipient.clone());
+    state.co_owner = None;
+    state.co_owner_split_bps = 0;
+    STATE.save(deps.storage, &state)?;
+    clear_approvals(deps.storage)?;
+    record_transfer(deps.storage, &state, &env, &prev_owner, &recipient)?;
 
-    Ok(Response::new()
+    let mut res = Response::new();
+    if let Some(hook) = &state.creator_transfer_hook {
+        let notify = WasmMsg::Execute {
+            contract_addr: hook.to_string(),
+            msg: to_binary(&CreatorTransferHookMsg::OwnershipTransferred {
+                from: prev_owner.clone(),
+                to: recipient.clone(),
+            })?,
+            funds: vec![],
+        };
+        // like the settlement callback, a failing hook must not block the transfer
+        res = res.add_submessage(SubMsg::reply_on_error(
+            notify,
+            CREATOR_TRANSFER_HOOK_REPLY_ID,
+        ));
+    }
+    Ok(res
+        .add_attribute("action", "transfer")
+        .add_attribute("prev_owner", prev_owner)
+        .add_attribute("new_owner", recipient.clone())
         .add_attribute("method", "try_transfer")
-        .add_attribute("new owner", recipient.clone()))
+        .add_attribute("new owner", recipient))
 }
 
-pub fn try_execute(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.owner {
-        return Err(ContractError::Unauthorized {});
+/// Appends a transfer to `TRANSFER_HISTORY`, dropping the oldest ones beyond `history_len`.
+#[cfg(feature = "transferable")]
+fn record_transfer(
+    storage: &mut dyn Storage,
+    state: &State,
+    env: &Env,
+    from: &Addr,
+    to: &Addr,
+) -> StdResult<()> {
+    let cap = state.history_len.unwrap_or(DEFAULT_HISTORY_LEN) as usize;
+    let mut history = TRANSFER_HISTORY.may_load(storage)?.unwrap_or_default();
+    history.push(TransferRecord {
+        from: from.clone(),
+        to: to.clone(),
+        height: env.block.height,
+    });
+    let excess = history.len().saturating_sub(cap);
+    history.drain(..excess);
+    TRANSFER_HISTORY.save(storage, &history)
+}
+
+#[cfg(feature = "executable")]
+pub fn try_execute(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = load_state(deps.storage)?;
+    let now = expiry_clock(deps.branch(), &state, &env)?;
+    match check_exercisable(deps.storage, &state, &env, &info, now) {
+        Err(ContractError::Expired {}) if state.lapse_to_creator => {
+            return lapse_to_creator(deps, state, info)
+        }
+        result => result?,
     }
-    if env.block.height >= state.expires {
-        return Err(ContractError::Expired {});
+    check_execution_condition(deps.as_ref(), &state)?;
+    let (payment, surplus) = check_payment(deps.storage, &state, &info.funds)?;
+    let refunds = match ESCROW.may_load(deps.storage)? {
+        Some(escrow) => {
+            ESCROW.remove(deps.storage);
+            refund_surplus(&state, &escrow.depositor, surplus)
+        }
+        None => refund_surplus(&state, &info.sender, surplus),
+    };
+
+    let near_expiry = is_near_expiry(&state, now);
+    let (res, hash) = settle(deps, &env, state, payment, refunds)?;
+    let res = res
+        .add_attribute("action", "execute")
+        .add_attribute("settlement_hash", hash.to_base64())
+        .add_attribute("method", "try_execute");
+    if near_expiry {
+        return Ok(res.add_attribute("near_expiry", "true"));
     }
-    if info.funds != state.counter_offer {
-        return Err(ContractError::DiffCounterOffer {
-            counter_offer: format!("{:?}", state.counter_offer),
+    Ok(res)
+}
+
+/// Hands an expired option back to its creator, refunding the funds sent with the execute and
+/// any deposits made towards it.
+#[cfg(feature = "executable")]
+fn lapse_to_creator(
+    deps: DepsMut,
+    mut state: State,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut refunds = release_deposits(deps.storage, &state)?;
+    if !info.funds.is_empty() {
+        refunds.push(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: info.funds,
         });
     }
+    state.paid_so_far = vec![];
+    let prev_owner = std::mem::replace(&mut state.owner, state.creator.clone());
+    state.co_owner = None;
+    state.co_owner_split_bps = 0;
+    STATE.save(deps.storage, &state)?;
+    clear_approvals(deps.storage)?;
 
-    let res = Response::new()
-        .add_message(BankMsg::Send {
-            to_address: state.creator.to_string(),
-            amount: state.counter_offer.clone(),
-        })
-        .add_message(BankMsg::Send {
-            to_address: state.owner.to_string(),
-            amount: state.collateral,
+    Ok(Response::new()
+        .add_messages(refunds)
+        .add_attribute("action", "lapse")
+        .add_attribute("prev_owner", prev_owner)
+        .add_attribute("new_owner", state.creator)
+        .add_attribute("method", "try_execute"))
+}
+
+#[cfg(feature = "executable")]
+pub fn try_pay_installment(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut state = load_state(deps.storage)?;
+    let now = expiry_clock(deps.branch(), &state, &env)?;
+    check_exercisable(deps.storage, &state, &env, &info, now)?;
+    if ESCROW.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::EscrowExists {});
+    }
+    let counter_denom = |c: &Coin| state.counter_offer.iter().any(|d| d.denom == c.denom);
+    if info.funds.is_empty() || !info.funds.iter().all(counter_denom) {
+        return Err(ContractError::DiffCounterOffer {
+            counter_offer: format!("{:?}", state.counter_offer),
         });
+    }
 
-    STATE.remove(deps.storage);
+    let installment = info.funds.clone();
+    let paid = merge_coins(std::mem::take(&mut state.paid_so_far), info.funds);
+    let surplus = match subtract_coins(&paid, &state.counter_offer) {
+        Some(surplus) => surplus,
+        None => {
+            state.paid_so_far = paid;
+            STATE.save(deps.storage, &state)?;
+            INSTALLMENTS.update(deps.storage, &info.sender, |paid| -> StdResult<_> {
+                Ok(merge_coins(paid.unwrap_or_default(), installment))
+            })?;
+            return Ok(Response::new()
+                .add_attribute("action", "pay_installment")
+                .add_attribute("method", "try_pay_installment"));
+        }
+    };
 
-    Ok(res.add_attribute("method", "try_execute"))
+    // fully paid: settle as if the whole counter offer had been sent to `Execute`, returning
+    // the surplus to whoever sent the final installment
+    check_execution_condition(deps.as_ref(), &state)?;
+    take_funded(deps.storage, &INSTALLMENTS)?;
+    let payment = state.counter_offer.clone();
+    check_accepted_denoms(&state, &payment)?;
+    let refunds = refund_surplus(&state, &info.sender, surplus);
+    let (res, hash) = settle(deps, &env, state, payment, refunds)?;
+    Ok(res
+        .add_attribute("action", "pay_installment")
+        .add_attribute("settlement_hash", hash.to_base64())
+        .add_attribute("method", "try_pay_installment"))
 }
 
-pub fn try_burn(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if env.block.height < state.expires {
-        return Err(ContractError::CustomError {
-            val: "Option not yet expired".to_string(),
-        });
-    }
-    if !info.funds.is_empty() {
-        return Err(ContractError::CustomError {
-            val: "dont send funds with burn".to_string(),
+/// Asks the `execution_condition` contract, if any, whether the option may settle now.
+#[cfg(feature = "executable")]
+fn check_execution_condition(deps: Deps, state: &State) -> Result<(), ContractError> {
+    if let Some((contract, msg)) = &state.execution_condition {
+        let request = QueryRequest::<Empty>::Wasm(WasmQuery::Smart {
+            contract_addr: contract.to_string(),
+            msg: msg.clone(),
         });
+        let met: bool = deps.querier.query(&request)?;
+        if !met {
+            return Err(ContractError::ConditionNotMet {});
+        }
     }
-
-    let res = Response::new().add_message(BankMsg::Send {
-        to_address: state.creator.to_string(),
-        amount: state.collateral,
-    });
-    Ok(res.add_attribute("method", "try_burn"))
+    Ok(())
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+/// Approvals are granted by an owner and do not survive a change of owner.
+fn clear_approvals(storage: &mut dyn Storage) -> StdResult<()> {
+    let operators = APPROVALS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for operator in operators {
+        APPROVALS.remove(storage, &operator);
     }
+    Ok(())
 }
 
-fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
-    let state = STATE.load(deps.storage)?;
-    Ok(state)
+/// True for the owner and for operators holding an unexpired approval.
+#[cfg(any(feature = "transferable", feature = "executable"))]
+fn can_act_for_owner(
+    storage: &dyn Storage,
+    state: &State,
+    env: &Env,
+    sender: &Addr,
+) -> StdResult<bool> {
+    if *sender == state.owner {
+        return Ok(true);
+    }
+    let approval = APPROVALS.may_load(storage, sender)?;
+    Ok(matches!(approval, Some(a) if is_live_approval(&a, env)))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary, Attribute, SubMsg};
+fn is_live_approval(approval: &Approval, env: &Env) -> bool {
+    !matches!(approval.expires, Some(expires) if env.block.height >= expires)
+}
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
-        let msg = InstantiateMsg {
-            counter_offer: coins(40, "ETH"),
-            expires: 100_000,
-        };
-        let info = mock_info("creator", &coins(1, "BTC"));
+fn query_oracle_time(deps: Deps, oracle: &Addr) -> StdResult<u64> {
+    let reported: TimeOracleResponse = deps
+        .querier
+        .query_wasm_smart(oracle, &TimeOracleQueryMsg::Time {})?;
+    Ok(reported.time)
+}
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+/// The point in time `expires` is measured against: the block height, or the time oracle's
+/// reading when one is configured. Readings never move back behind the last cached one.
+fn current_time(deps: Deps, state: &State, env: &Env) -> StdResult<u64> {
+    let oracle = match &state.time_oracle {
+        Some(oracle) => oracle,
+        None => return Ok(env.block.height),
+    };
+    let cached = ORACLE_TIME.may_load(deps.storage)?;
+    if let Some(cached) = &cached {
+        if cached.height == env.block.height {
+            return Ok(cached.time);
+        }
+    }
+    let reported = query_oracle_time(deps, oracle)?;
+    Ok(match cached {
+        Some(cached) => reported.max(cached.time),
+        None => reported,
+    })
+}
 
-        let value: ConfigResponse = from_binary(&res).unwrap();
+/// `current_time` for handlers, caching the oracle reading for the rest of the block.
+fn expiry_clock(deps: DepsMut, state: &State, env: &Env) -> Result<u64, ContractError> {
+    let time = current_time(deps.as_ref(), state, env)?;
+    if state.time_oracle.is_some() {
+        ORACLE_TIME.save(
+            deps.storage,
+            &OracleTime {
+                height: env.block.height,
+                time,
+            },
+        )?;
+    }
+    Ok(time)
+}
 
-        assert_eq!(100_000, value.expires);
-        assert_eq!("creator", value.owner);
-        assert_eq!("creator", value.creator);
-        assert_eq!(coins(1, "BTC"), value.collateral);
-        assert_eq!(coins(40, "ETH"), value.counter_offer);
+/// The sender, expiry and minimum life checks shared by every way of exercising the option,
+/// with `now` read from `expiry_clock`.
+#[cfg(feature = "executable")]
+fn check_exercisable(
+    storage: &dyn Storage,
+    state: &State,
+    env: &Env,
+    info: &MessageInfo,
+    now: u64,
+) -> Result<(), ContractError> {
+    if !can_act_for_owner(storage, state, env, &info.sender)? {
+        return Err(ContractError::NotOwner {});
+    }
+    if state.is_expired(now) {
+        return Err(ContractError::Expired {});
     }
+    let available_at = state
+        .created_height
+        .checked_add(state.min_life_blocks)
+        .ok_or(ContractError::Overflow {})?;
+    if env.block.height < available_at {
+        return Err(ContractError::TooEarly { available_at });
+    }
+    Ok(())
+}
 
-    #[test]
-    fn transfer() {
-        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+/// Pays `payment` to the creator and the collateral to the owner, sends `refunds`, and
+/// removes the option. Returns the response and the settlement hash for the caller to tag.
+#[cfg(feature = "executable")]
+fn settle(
+    deps: DepsMut,
+    env: &Env,
+    state: State,
+    payment: Vec<Coin>,
+    refunds: Vec<BankMsg>,
+) -> Result<(Response, Binary), ContractError> {
+    let mut penalty = vec![];
+    let mut collateral = state.collateral.clone();
+    if env.block.height < state.penalty_until_height {
+        let (cut, rest) = split_coins(
+            &state.collateral,
+            state.early_exercise_penalty_bps,
+            dust_to(&state, DustPolicy::ToCreator),
+        );
+        penalty = cut;
+        collateral = rest;
+    }
+    let mut co_owner_share = vec![];
+    if state.co_owner.is_some() {
+        // the owner's part is the share of this split, so it takes the dust under `ToOwner`
+        let (kept, rest) = split_coins(
+            &collateral,
+            MAX_BPS - state.co_owner_split_bps,
+            dust_to(&state, DustPolicy::ToOwner),
+        );
+        co_owner_share = rest;
+        collateral = kept;
+    }
 
-        let msg = InstantiateMsg {
-            counter_offer: coins(40, "ETH"),
-            expires: 100_000,
-        };
-        let info = mock_info("creator", &coins(1, "BTC"));
+    let hash = settlement_hash(
+        &state.creator,
+        &state.owner,
+        &state.collateral,
+        &payment,
+        env.block.height,
+    )?;
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
-        let _res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let (fee, proceeds) = execute_fee_split(deps.storage, &state, &payment)?;
+    if !fee.is_empty() {
+        let accrued = FEES_ACCRUED.may_load(deps.storage)?.unwrap_or_default();
+        FEES_ACCRUED.save(deps.storage, &merge_coins(accrued, fee))?;
+    }
+    let callback = match &state.creator_callback {
+        Some(callback) => Some(WasmMsg::Execute {
+            contract_addr: callback.to_string(),
+            msg: to_binary(&CreatorCallbackMsg::OptionSettled {
+                counter_offer: proceeds.clone(),
+            })?,
+            funds: vec![],
+        }),
+        None => None,
+    };
 
-        // random cant transfer
-        let info = mock_info("anyone", &[]);
-        let msg = ExecuteMsg::Transfer {
-            recipient: Addr::unchecked("anyone"),
+    let mut creator_payout = merge_coins(proceeds, penalty);
+    let mut res = Response::new();
+    if let (Some(router), Some(ask_denom)) = (&state.swap_router, &state.desired_proceeds_denom) {
+        // coins already in the desired denom skip the router; a failed swap or one returning
+        // less than `min_swap_output` fails the whole execute
+        let (direct, offer): (Vec<Coin>, Vec<Coin>) = creator_payout
+            .into_iter()
+            .partition(|c| &c.denom == ask_denom);
+        creator_payout = direct;
+        if !offer.is_empty() {
+            res = res.add_message(WasmMsg::Execute {
+                contract_addr: router.to_string(),
+                msg: to_binary(&SwapExecuteMsg::Swap {
+                    ask_denom: ask_denom.clone(),
+                    min_output: state.min_swap_output,
+                    recipient: state.creator.clone(),
+                })?,
+                funds: offer,
+            });
+        }
+    }
+    if let Some(distribution) = &state.distribution_contract {
+        // the creator's leg is recorded and forwarded by the distribution contract; if that
+        // fails, `reply` pays the creator directly from the pending record saved here
+        PENDING_DISTRIBUTION.save(
+            deps.storage,
+            &PendingDistribution {
+                beneficiary: state.creator.clone(),
+                funds: creator_payout.clone(),
+            },
+        )?;
+        let forward = WasmMsg::Execute {
+            contract_addr: distribution.to_string(),
+            msg: to_binary(&DistributionExecuteMsg::RecordAndForward {
+                beneficiary: state.creator.clone(),
+                memo: format!("option settlement {}", hash.to_base64()),
+            })?,
+            funds: creator_payout,
         };
-        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-        match err {
-            ContractError::Unauthorized {} => {}
-            _ => panic!("Must return unauthorized error"),
+        res = res.add_submessage(SubMsg::reply_always(forward, DISTRIBUTION_REPLY_ID));
+        if !collateral.is_empty() {
+            res = res.add_message(BankMsg::Send {
+                to_address: state.owner.to_string(),
+                amount: collateral,
+            });
         }
-
-        // owner can transfer
-        let _info = mock_info("creator", &[]);
-        let _msg = ExecuteMsg::Transfer {
-            recipient: Addr::unchecked("someone"),
+    } else if let Some(vesting) = &state.vesting_contract {
+        // like the distribution contract, a failed vesting is answered in `reply` by paying
+        // the creator directly
+        PENDING_VESTING.save(
+            deps.storage,
+            &PendingDistribution {
+                beneficiary: state.creator.clone(),
+                funds: creator_payout.clone(),
+            },
+        )?;
+        let forward = WasmMsg::Execute {
+            contract_addr: vesting.to_string(),
+            msg: to_binary(&VestingExecuteMsg::CreateVesting {
+                beneficiary: state.creator.clone(),
+            })?,
+            funds: creator_payout,
         };
-        let success = execute(deps.as_mut(), mock_env(), _info, _msg).unwrap();
-        assert_eq!(success.attributes.len(), 2);
-        assert_eq!(
-            success.attributes[1],
-            Attribute::new("new owner", "someone")
-        );
-
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-        let value: ConfigResponse = from_binary(&res).unwrap();
+        res = res.add_submessage(SubMsg::reply_always(forward, VESTING_REPLY_ID));
+        if !collateral.is_empty() {
+            res = res.add_message(BankMsg::Send {
+                to_address: state.owner.to_string(),
+                amount: collateral,
+            });
+        }
+    } else if state.two_phase_settlement && !collateral.is_empty() {
+        // the collateral is only sent from `reply` once the creator's payment has succeeded;
+        // if it fails the whole execute reverts
+        PENDING_COLLATERAL.save(
+            deps.storage,
+            &PendingDistribution {
+                beneficiary: state.owner.clone(),
+                funds: collateral,
+            },
+        )?;
+        let pay_creator = BankMsg::Send {
+            to_address: state.creator.to_string(),
+            amount: creator_payout,
+        };
+        res = res.add_submessage(SubMsg::reply_on_success(pay_creator, CREATOR_PAID_REPLY_ID));
+    } else if state.creator == state.owner
+        && creator_payout
+            .iter()
+            .any(|c| collateral.iter().any(|d| d.denom == c.denom))
+    {
+        // both legs go to the same address: send them as one message so overlapping denoms
+        // are summed exactly once
+        res = res.add_message(BankMsg::Send {
+            to_address: state.creator.to_string(),
+            amount: merge_coins(creator_payout, collateral),
+        });
+    } else {
+        if !creator_payout.is_empty() {
+            res = res.add_message(BankMsg::Send {
+                to_address: state.creator.to_string(),
+                amount: creator_payout,
+            });
+        }
+        if !collateral.is_empty() {
+            res = res.add_message(BankMsg::Send {
+                to_address: state.owner.to_string(),
+                amount: collateral,
+            });
+        }
+    }
+    if let Some(co_owner) = &state.co_owner {
+        if !co_owner_share.is_empty() {
+            res = res.add_message(BankMsg::Send {
+                to_address: co_owner.to_string(),
+                amount: co_owner_share,
+            });
+        }
+    }
+    let reward = take_funded(deps.storage, &EXECUTOR_REWARDS)?
+        .into_iter()
+        .fold(vec![], |total, (_, reward)| merge_coins(total, reward));
+    if !reward.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: state.owner.to_string(),
+            amount: reward,
+        });
+    }
+    res = res.add_messages(refunds);
 
-        assert_eq!("someone", value.owner);
-        assert_eq!("creator", value.creator);
+    if let Some(callback) = callback {
+        // a failing callback is reverted on its own and reported by `reply`, so it cannot
+        // block the settlement above
+        res = res.add_submessage(SubMsg::reply_on_error(callback, CREATOR_CALLBACK_REPLY_ID));
     }
 
-    #[test]
-    fn execute_test() {
-        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+    STATE.remove(deps.storage);
 
-        let counter_offer = coins(40, "ETH");
-        let msg = InstantiateMsg {
-            counter_offer: counter_offer.clone(),
-            expires: 100_000,
-        };
-        let collateral = coins(1, "BTC");
-        let info = mock_info("creator", &collateral);
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    Ok((res, hash))
+}
 
-        assert_eq!(0, res.messages.len());
-        let _res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+#[cfg(feature = "executable")]
+pub fn try_escrow_counter_offer(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = load_state(deps.storage)?;
+    if state.is_expired(expiry_clock(deps.branch(), &state, &env)?) {
+        return Err(ContractError::Expired {});
+    }
+    if ESCROW.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::EscrowExists {});
+    }
+    // an escrow would block both `Execute` and further installments
+    if !state.paid_so_far.is_empty() {
+        return Err(ContractError::InstallmentsInProgress {});
+    }
+    if subtract_coins(&info.funds, &state.counter_offer).is_none() {
+        return Err(ContractError::DiffCounterOffer {
+            counter_offer: format!("{:?}", state.counter_offer),
+        });
+    }
+    ESCROW.save(
+        deps.storage,
+        &Escrow {
+            depositor: info.sender.clone(),
+            funds: info.funds,
+        },
+    )?;
 
-        // random cant execute
-        let info = mock_info("anyone", &counter_offer);
-        let msg = ExecuteMsg::Transfer {
-            recipient: Addr::unchecked("anyone"),
-        };
-        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-        match err {
-            ContractError::Unauthorized {} => {}
+    Ok(Response::new()
+        .add_attribute("action", "escrow_counter_offer")
+        .add_attribute("depositor", info.sender)
+        .add_attribute("method", "try_escrow_counter_offer"))
+}
+
+/// `coins` minus `sub`, or `None` if `coins` does not cover every coin of `sub`.
+fn subtract_coins(coins: &[Coin], sub: &[Coin]) -> Option<Vec<Coin>> {
+    let mut rest = coins.to_vec();
+    for coin in sub {
+        let existing = rest.iter_mut().find(|c| c.denom == coin.denom)?;
+        existing.amount = existing.amount.checked_sub(coin.amount).ok()?;
+    }
+    rest.retain(|c| !c.amount.is_zero());
+    Some(rest)
+}
+
+/// Removes any escrowed counter offer and executor rewards and returns the messages refunding
+/// them, and any installments paid so far, when the option ends without being exercised.
+fn release_deposits(storage: &mut dyn Storage, state: &State) -> StdResult<Vec<BankMsg>> {
+    let mut refunds = vec![];
+    for (funder, reward) in take_funded(storage, &EXECUTOR_REWARDS)? {
+        refunds.push(BankMsg::Send {
+            to_address: funder.to_string(),
+            amount: reward,
+        });
+    }
+    if let Some(escrow) = ESCROW.may_load(storage)? {
+        ESCROW.remove(storage);
+        refunds.push(BankMsg::Send {
+            to_address: escrow.depositor.to_string(),
+            amount: escrow.funds,
+        });
+    }
+    let mut unattributed = state.paid_so_far.clone();
+    for (payer, paid) in take_funded(storage, &INSTALLMENTS)? {
+        unattributed = subtract_coins(&unattributed, &paid).unwrap_or_default();
+        refunds.push(BankMsg::Send {
+            to_address: payer.to_string(),
+            amount: paid,
+        });
+    }
+    // installments paid before payers were recorded go back to the owner
+    if !unattributed.is_empty() {
+        refunds.push(BankMsg::Send {
+            to_address: state.owner.to_string(),
+            amount: unattributed,
+        });
+    }
+    Ok(refunds)
+}
+
+/// Sends `surplus` back to `recipient`, except coins below `min_refund_threshold`, which are
+/// not worth refunding and go to the creator instead.
+#[cfg(feature = "executable")]
+fn refund_surplus(state: &State, recipient: &Addr, surplus: Vec<Coin>) -> Vec<BankMsg> {
+    let (dust, refund): (Vec<Coin>, Vec<Coin>) = surplus.into_iter().partition(|c| {
+        matches!(&state.min_refund_threshold, Some(t) if t.denom == c.denom && c.amount < t.amount)
+    });
+    let mut msgs = vec![];
+    if !refund.is_empty() {
+        msgs.push(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: refund,
+        });
+    }
+    if !dust.is_empty() {
+        msgs.push(BankMsg::Send {
+            to_address: state.creator.to_string(),
+            amount: dust,
+        });
+    }
+    msgs
+}
+
+/// The checks `Execute` makes before settling with `funds`, shared with `WouldAccept` so the
+/// query never accepts what execute rejects. Returns the payment and the surplus, which
+/// comes out of the escrow when the counter offer was escrowed.
+fn check_payment(
+    storage: &dyn Storage,
+    state: &State,
+    funds: &[Coin],
+) -> Result<(Vec<Coin>, Vec<Coin>), ContractError> {
+    if !state.paid_so_far.is_empty() {
+        return Err(ContractError::InstallmentsInProgress {});
+    }
+    let escrow = ESCROW.may_load(storage)?;
+    let (payment, surplus) = match &escrow {
+        Some(escrow) => {
+            if !funds.is_empty() {
+                return Err(ContractError::EscrowExists {});
+            }
+            // the escrow was checked to cover the counter offer when it was deposited
+            let excess = subtract_coins(&escrow.funds, &state.counter_offer).ok_or_else(|| {
+                ContractError::DiffCounterOffer {
+                    counter_offer: format!("{:?}", state.counter_offer),
+                }
+            })?;
+            (state.counter_offer.clone(), excess)
+        }
+        None => match_payment(storage, state, funds)?,
+    };
+    check_accepted_denoms(state, &payment)?;
+    Ok((payment, surplus))
+}
+
+/// Rejects a payment in a denom the admin has since removed from `accepted_denoms`.
+fn check_accepted_denoms(state: &State, payment: &[Coin]) -> Result<(), ContractError> {
+    if let Some(accepted) = &state.accepted_denoms {
+        if let Some(coin) = payment.iter().find(|c| !accepted.contains(&c.denom)) {
+            return Err(ContractError::DenomNoLongerAccepted {
+                denom: coin.denom.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Splits `funds` into the payment to settle with and the surplus to refund. An exact match of
+/// any accepted counter offer is taken as is; otherwise `funds` must cover the primary counter
+/// offer, which is taken exactly, with every other coin being surplus.
+fn match_payment(
+    storage: &dyn Storage,
+    state: &State,
+    funds: &[Coin],
+) -> Result<(Vec<Coin>, Vec<Coin>), ContractError> {
+    match check_counter_offer(storage, state, funds) {
+        Ok(()) => Ok((funds.to_vec(), vec![])),
+        Err(err) => match subtract_coins(funds, &state.counter_offer) {
+            Some(surplus) => Ok((state.counter_offer.clone(), surplus)),
+            None => Err(err),
+        },
+    }
+}
+
+/// Checks `funds` against the counter offer and any alternatives, after normalizing denoms.
+fn check_counter_offer(
+    storage: &dyn Storage,
+    state: &State,
+    funds: &[Coin],
+) -> Result<(), ContractError> {
+    let funds = normalize_coins(storage, funds)?;
+    for counter_offer in
+        std::iter::once(&state.counter_offer).chain(&state.alternative_counter_offers)
+    {
+        if funds == normalize_coins(storage, counter_offer)? {
+            return Ok(());
+        }
+    }
+    if state.alternative_counter_offers.is_empty() {
+        Err(ContractError::DiffCounterOffer {
+            counter_offer: format!("{:?}", state.counter_offer),
+        })
+    } else {
+        Err(ContractError::NoMatchingCounterOffer {})
+    }
+}
+
+/// Splits each coin into a `bps` share and the remainder, dropping zero amounts from both.
+/// The share is rounded down unless `round_up`, so rounding dust lands on exactly one side
+/// and the two always sum to the input.
+fn split_coins(coins: &[Coin], bps: u16, round_up: bool) -> (Vec<Coin>, Vec<Coin>) {
+    let mut cut = vec![];
+    let mut rest = vec![];
+    for coin in coins {
+        let share = if round_up {
+            coin.amount - coin.amount.multiply_ratio(MAX_BPS - bps, MAX_BPS)
+        } else {
+            coin.amount.multiply_ratio(bps, MAX_BPS)
+        };
+        if !share.is_zero() {
+            cut.push(Coin::new(share.u128(), coin.denom.clone()));
+        }
+        if share < coin.amount {
+            rest.push(Coin::new((coin.amount - share).u128(), coin.denom.clone()));
+        }
+    }
+    (cut, rest)
+}
+
+/// Whether `party`, who receives the share side of a split, is the configured dust recipient.
+fn dust_to(state: &State, party: DustPolicy) -> bool {
+    state.dust_recipient == Some(party)
+}
+
+/// Adds `extra` into `coins`, summing amounts of matching denoms.
+fn merge_coins(mut coins: Vec<Coin>, extra: Vec<Coin>) -> Vec<Coin> {
+    for coin in extra {
+        match coins.iter_mut().find(|c| c.denom == coin.denom) {
+            Some(existing) => existing.amount += coin.amount,
+            None => coins.push(coin),
+        }
+    }
+    coins
+}
+
+/// Deterministic sha256 digest of the parameters an option settled with, for auditing.
+pub fn settlement_hash(
+    creator: &Addr,
+    owner: &Addr,
+    collateral: &[Coin],
+    counter_offer: &[Coin],
+    height: u64,
+) -> StdResult<Binary> {
+    let preimage = to_vec(&(creator, owner, collateral, counter_offer, height))?;
+    Ok(Binary::from(Sha256::digest(&preimage).as_slice()))
+}
+
+/// Splits the counter offer `payment` into the execute fee kept by the contract and what
+/// the creator is paid.
+fn execute_fee_split(
+    storage: &dyn Storage,
+    state: &State,
+    payment: &[Coin],
+) -> StdResult<(Vec<Coin>, Vec<Coin>)> {
+    let bps = FEE_CONFIG
+        .may_load(storage)?
+        .map_or(0, |config| config.execute_fee_bps);
+    Ok(split_coins(
+        payment,
+        bps,
+        dust_to(state, DustPolicy::ToFeeRecipient),
+    ))
+}
+
+/// Maps an aliased denom onto its canonical denom, leaving other coins untouched.
+pub fn normalize_coin(storage: &dyn Storage, coin: &Coin) -> StdResult<Coin> {
+    let denom = DENOM_ALIASES
+        .may_load(storage, &coin.denom)?
+        .unwrap_or_else(|| coin.denom.clone());
+    Ok(Coin::new(coin.amount.u128(), denom))
+}
+
+/// Normalizes every coin and merges the result into a denom-sorted list, so that
+/// equivalent payments compare equal regardless of aliasing or ordering.
+fn normalize_coins(storage: &dyn Storage, coins: &[Coin]) -> StdResult<Vec<Coin>> {
+    let mut normalized = vec![];
+    for coin in coins {
+        normalized = merge_coins(normalized, vec![normalize_coin(storage, coin)?]);
+    }
+    normalized.sort_by(|a, b| a.denom.cmp(&b.denom));
+    Ok(normalized)
+}
+
+/// The expiry, confirmation buffer and no-funds checks shared by both burns, with `now` read
+/// from `expiry_clock`.
+#[cfg(feature = "burnable")]
+fn check_burnable(state: &State, info: &MessageInfo, now: u64) -> Result<(), ContractError> {
+    if !state.is_expired(now) {
+        return Err(ContractError::CustomError {
+            val: "Option not yet expired".to_string(),
+        });
+    }
+    let available_at = state
+        .expired_at()
+        .checked_add(state.burn_confirmation_blocks)
+        .ok_or(ContractError::Overflow {})?;
+    if now < available_at {
+        return Err(ContractError::BurnTooEarly { available_at });
+    }
+    if !info.funds.is_empty() {
+        return Err(ContractError::CustomError {
+            val: "dont send funds with burn".to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(feature = "burnable")]
+pub fn try_burn(mut deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let state = load_state(deps.storage)?;
+    let now = expiry_clock(deps.branch(), &state, &env)?;
+    check_burnable(&state, &info, now)?;
+
+    let (fee, refund) = burn_split(deps.as_ref(), &env, &state)?;
+    let mut res = Response::new().add_attribute("action", "burn");
+    // chains reject a bank send with no coins, so an empty refund is not sent at all
+    if refund.is_empty() {
+        res = res.add_attribute("no_collateral", "true");
+    } else {
+        res = res.add_submessage(burn_refund(deps.storage, &state, refund)?);
+    }
+    if let (Some(recipient), false) = (&state.burn_fee_recipient, fee.is_empty()) {
+        res = res.add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: fee,
+        });
+    }
+
+    res = res.add_messages(release_deposits(deps.storage, &state)?);
+
+    // a burned option is settled, so it must not be burned or recovered again
+    STATE.remove(deps.storage);
+
+    Ok(res.add_attribute("method", "try_burn"))
+}
+
+/// Returns burned collateral to the creator, through `unwrap_on_burn` when set.
+#[cfg(feature = "burnable")]
+fn burn_refund(storage: &mut dyn Storage, state: &State, refund: Vec<Coin>) -> StdResult<SubMsg> {
+    let unwrap = match &state.unwrap_on_burn {
+        Some(unwrap) => unwrap,
+        None => {
+            return Ok(SubMsg::new(BankMsg::Send {
+                to_address: state.creator.to_string(),
+                amount: refund,
+            }))
+        }
+    };
+    // as with the distribution contract, a failed unwrap is answered in `reply` by
+    // returning the collateral as is
+    PENDING_UNWRAP.save(
+        storage,
+        &PendingDistribution {
+            beneficiary: state.creator.clone(),
+            funds: refund.clone(),
+        },
+    )?;
+    let forward = WasmMsg::Execute {
+        contract_addr: unwrap.to_string(),
+        msg: to_binary(&UnwrapExecuteMsg::Unwrap {
+            recipient: state.creator.clone(),
+        })?,
+        funds: refund,
+    };
+    Ok(SubMsg::reply_always(forward, UNWRAP_REPLY_ID))
+}
+
+#[cfg(feature = "burnable")]
+pub fn try_burn_available(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut state = load_state(deps.storage)?;
+    let now = expiry_clock(deps.branch(), &state, &env)?;
+    check_burnable(&state, &info, now)?;
+
+    let (frozen, available): (Vec<Coin>, Vec<Coin>) = std::mem::take(&mut state.collateral)
+        .into_iter()
+        .partition(|c| state.frozen_denoms.contains(&c.denom));
+    if available.is_empty() {
+        return Err(ContractError::CustomError {
+            val: "no unfrozen collateral to burn".to_string(),
+        });
+    }
+    let available = if state.burn_actual_balance {
+        held_collateral(deps.as_ref(), &env, &state, &available)?
+    } else {
+        available
+    };
+
+    let (fee, refund) = split_coins(
+        &available,
+        state.burn_fee_bps,
+        dust_to(&state, DustPolicy::ToFeeRecipient),
+    );
+    let mut res = Response::new();
+    if !refund.is_empty() {
+        res = res.add_submessage(burn_refund(deps.storage, &state, refund)?);
+    }
+    if let (Some(recipient), false) = (&state.burn_fee_recipient, fee.is_empty()) {
+        res = res.add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: fee,
+        });
+    }
+
+    if frozen.is_empty() {
+        res = res.add_messages(release_deposits(deps.storage, &state)?);
+        STATE.remove(deps.storage);
+    } else {
+        state.collateral = frozen;
+        STATE.save(deps.storage, &state)?;
+    }
+
+    Ok(res
+        .add_attribute("action", "burn_available")
+        .add_attribute("method", "try_burn_available"))
+}
+
+/// What the contract actually holds in the denoms of `collateral`, less the funds it holds
+/// for others: escrowed counter offers, installments and executor rewards are refunded to
+/// whoever paid them by `release_deposits`, and accrued fees belong to the fee admin, so none
+/// of them may be burned or counted as collateral.
+fn held_collateral(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    collateral: &[Coin],
+) -> StdResult<Vec<Coin>> {
+    let mut owed = EXECUTOR_REWARDS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, reward)| reward))
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .fold(state.paid_so_far.clone(), merge_coins);
+    if let Some(escrow) = ESCROW.may_load(deps.storage)? {
+        owed = merge_coins(owed, escrow.funds);
+    }
+    if let Some(fees) = FEES_ACCRUED.may_load(deps.storage)? {
+        owed = merge_coins(owed, fees);
+    }
+    let mut held = vec![];
+    for coin in collateral {
+        let mut balance = deps
+            .querier
+            .query_balance(&env.contract.address, &coin.denom)?;
+        if let Some(owed) = owed.iter().find(|o| o.denom == coin.denom) {
+            balance.amount = balance.amount.saturating_sub(owed.amount);
+        }
+        if !balance.amount.is_zero() {
+            held.push(balance);
+        }
+    }
+    Ok(held)
+}
+
+/// Splits the collateral into the burn fee and what is refunded to the creator.
+fn burn_split(deps: Deps, env: &Env, state: &State) -> StdResult<(Vec<Coin>, Vec<Coin>)> {
+    let collateral = if state.burn_actual_balance {
+        held_collateral(deps, env, state, &state.collateral)?
+    } else {
+        state.collateral.clone()
+    };
+    Ok(split_coins(
+        &collateral,
+        state.burn_fee_bps,
+        dust_to(state, DustPolicy::ToFeeRecipient),
+    ))
+}
+
+/// What the creator gets back out of the collateral on burn.
+fn creator_refund(deps: Deps, env: &Env, state: &State) -> StdResult<Vec<Coin>> {
+    Ok(burn_split(deps, env, state)?.1)
+}
+
+pub fn try_add_collateral(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut state = load_state(deps.storage)?;
+    if info.sender != state.creator {
+        return Err(ContractError::NotCreator {});
+    }
+    if state.is_expired(expiry_clock(deps.branch(), &state, &env)?) {
+        return Err(ContractError::Expired {});
+    }
+    if info.funds.is_empty() {
+        return Err(ContractError::CustomError {
+            val: "no collateral sent".to_string(),
+        });
+    }
+
+    for coin in info.funds {
+        let existing = state
+            .collateral
+            .iter_mut()
+            .find(|c| c.denom == coin.denom)
+            .ok_or(ContractError::NewDenomNotAllowed {})?;
+        existing.amount += coin.amount;
+    }
+    if state.topup_extends_expiry {
+        state.expires = state
+            .expires
+            .checked_add(state.extension_per_topup_blocks)
+            .ok_or(ContractError::Overflow {})?;
+    }
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_collateral")
+        .add_attribute("method", "try_add_collateral"))
+}
+
+#[cfg(feature = "executable")]
+pub fn try_add_executor_reward(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = load_state(deps.storage)?;
+    if state.is_expired(expiry_clock(deps.branch(), &state, &env)?) {
+        return Err(ContractError::Expired {});
+    }
+    if info.funds.is_empty() {
+        return Err(ContractError::CustomError {
+            val: "no reward sent".to_string(),
+        });
+    }
+    EXECUTOR_REWARDS.update(deps.storage, &info.sender, |funded| -> StdResult<_> {
+        Ok(merge_coins(funded.unwrap_or_default(), info.funds.clone()))
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_executor_reward")
+        .add_attribute("method", "try_add_executor_reward"))
+}
+
+/// Removes every entry of a by-funder map such as `EXECUTOR_REWARDS`, returning who funded
+/// what.
+fn take_funded(
+    storage: &mut dyn Storage,
+    funded: &Map<&Addr, Vec<Coin>>,
+) -> StdResult<Vec<(Addr, Vec<Coin>)>> {
+    let entries = funded
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (funder, _) in &entries {
+        funded.remove(storage, funder);
+    }
+    Ok(entries)
+}
+
+pub fn try_set_metadata_uri(
+    deps: DepsMut,
+    info: MessageInfo,
+    uri: Option<String>,
+) -> Result<Response, ContractError> {
+    if let Some(uri) = &uri {
+        validate_metadata_uri(uri)?;
+    }
+    update_state(deps.storage, |mut state| {
+        if info.sender != state.creator {
+            return Err(ContractError::NotCreator {});
+        }
+        state.metadata_uri = uri;
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_metadata_uri")
+        .add_attribute("method", "try_set_metadata_uri"))
+}
+
+pub fn try_propose_creator_change(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_creator: Addr,
+) -> Result<Response, ContractError> {
+    update_state(deps.storage, |mut state| {
+        if info.sender != state.creator {
+            return Err(ContractError::NotCreator {});
+        }
+        state.pending_creator = Some(new_creator.clone());
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_creator_change")
+        .add_attribute("pending_creator", new_creator)
+        .add_attribute("method", "try_propose_creator_change"))
+}
+
+pub fn try_accept_creator_change(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    update_state(deps.storage, |mut state| {
+        if state.pending_creator.as_ref() != Some(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        state.creator = info.sender.clone();
+        state.pending_creator = None;
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_creator_change")
+        .add_attribute("new_creator", info.sender.clone())
+        .add_attribute("method", "try_accept_creator_change")
+        .add_attribute("new creator", info.sender))
+}
+
+pub fn try_set_frozen_denoms(
+    deps: DepsMut,
+    info: MessageInfo,
+    denoms: Vec<String>,
+) -> Result<Response, ContractError> {
+    update_state(deps.storage, |mut state| {
+        if state.admin.as_ref() != Some(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        state.frozen_denoms = denoms;
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_frozen_denoms")
+        .add_attribute("method", "try_set_frozen_denoms"))
+}
+
+pub fn try_set_accepted_denoms(
+    deps: DepsMut,
+    info: MessageInfo,
+    denoms: Option<Vec<String>>,
+) -> Result<Response, ContractError> {
+    update_state(deps.storage, |mut state| {
+        if state.admin.as_ref() != Some(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        state.accepted_denoms = denoms;
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_accepted_denoms")
+        .add_attribute("method", "try_set_accepted_denoms"))
+}
+
+pub fn try_abort_due_to_frozen_denom(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    let state = load_state(deps.storage)?;
+    if info.sender != state.creator && info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !state.frozen_denoms.contains(&denom)
+        || !state.counter_offer.iter().any(|c| c.denom == denom)
+    {
+        return Err(ContractError::DenomNotFrozen { denom });
+    }
+
+    let deposits = release_deposits(deps.storage, &state)?;
+    let mut res = Response::new()
+        .add_attribute("action", "abort_due_to_frozen_denom")
+        .add_attribute("denom", denom);
+    // as in burn, an empty bank send would be rejected
+    if state.collateral.is_empty() {
+        res = res.add_attribute("no_collateral", "true");
+    } else {
+        res = res.add_message(BankMsg::Send {
+            to_address: state.creator.to_string(),
+            amount: state.collateral,
+        });
+    }
+
+    STATE.remove(deps.storage);
+
+    Ok(res
+        .add_messages(deposits)
+        .add_attribute("method", "try_abort_due_to_frozen_denom"))
+}
+
+pub fn try_recover(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let state = load_state(deps.storage)?;
+    let now = expiry_clock(deps.branch(), &state, &env)?;
+    let recovery_address = state
+        .recovery_address
+        .clone()
+        .ok_or(ContractError::NoRecoveryAddress {})?;
+    let available_at = state
+        .expired_at()
+        .checked_add(state.recovery_after_blocks)
+        .ok_or(ContractError::Overflow {})?;
+    if now < available_at {
+        return Err(ContractError::RecoverTooEarly { available_at });
+    }
+
+    let deposits = release_deposits(deps.storage, &state)?;
+    let mut res = Response::new()
+        .add_attribute("action", "recover")
+        .add_attribute("recipient", &recovery_address);
+    // as in burn, an empty bank send would be rejected
+    if state.collateral.is_empty() {
+        res = res.add_attribute("no_collateral", "true");
+    } else {
+        res = res.add_message(BankMsg::Send {
+            to_address: recovery_address.to_string(),
+            amount: state.collateral,
+        });
+    }
+
+    STATE.remove(deps.storage);
+
+    Ok(res
+        .add_messages(deposits)
+        .add_attribute("method", "try_recover"))
+}
+
+pub fn try_relist(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_counter_offer: Vec<Coin>,
+    new_expires: u64,
+) -> Result<Response, ContractError> {
+    let mut state = load_state(deps.storage)?;
+    if info.sender != state.creator {
+        return Err(ContractError::NotCreator {});
+    }
+    let now = expiry_clock(deps.branch(), &state, &env)?;
+    if !state.is_expired(now) {
+        return Err(ContractError::CustomError {
+            val: "Option not yet expired".to_string(),
+        });
+    }
+    // the new terms must pass the same counter offer checks as at instantiate
+    check_counter_offer_limits(
+        &new_counter_offer,
+        state.max_counter_offer_denoms,
+        state.settlement_denom.as_ref(),
+        state.lot_size.as_ref(),
+    )?;
+    if state.reject_at_the_money && is_at_the_money(&state.collateral, &new_counter_offer) {
+        return Err(ContractError::AtTheMoneyNotAllowed {});
+    }
+    if let Some(oracle) = &state.oracle {
+        check_oracle_price(
+            deps.as_ref(),
+            oracle,
+            &state.collateral,
+            &new_counter_offer,
+            state.max_mispricing_bps,
+        )?;
+    }
+    if new_expires <= now {
+        return Err(ContractError::Expired {});
+    }
+
+    // the old listing ends here: its deposits are returned and its holder loses the option
+    let refunds = release_deposits(deps.storage, &state)?;
+    state.paid_so_far = vec![];
+    state.counter_offer = new_counter_offer;
+    state.counter_offer_revision = state
+        .counter_offer_revision
+        .checked_add(1)
+        .ok_or(ContractError::Overflow {})?;
+    state.alternative_counter_offers = vec![];
+    state.expires = new_expires;
+    state.owner = state.creator.clone();
+    state.co_owner = None;
+    state.co_owner_split_bps = 0;
+    STATE.save(deps.storage, &state)?;
+    clear_approvals(deps.storage)?;
+
+    Ok(Response::new()
+        .add_messages(refunds)
+        .add_attribute("action", "relist")
+        .add_attribute("expires", new_expires.to_string())
+        .add_attribute("method", "try_relist"))
+}
+
+pub fn try_soulbind(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    update_state(deps.storage, |mut state| {
+        if info.sender != state.owner {
+            return Err(ContractError::NotOwner {});
+        }
+        state.soulbound = true;
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "soulbind")
+        .add_attribute("method", "try_soulbind"))
+}
+
+pub fn try_set_co_owner(
+    deps: DepsMut,
+    info: MessageInfo,
+    co_owner: Option<Addr>,
+    split_bps: u16,
+) -> Result<Response, ContractError> {
+    if split_bps > MAX_BPS {
+        return Err(ContractError::InvalidBasisPoints {});
+    }
+    update_state(deps.storage, |mut state| {
+        if info.sender != state.owner {
+            return Err(ContractError::NotOwner {});
+        }
+        state.co_owner_split_bps = if co_owner.is_some() { split_bps } else { 0 };
+        state.co_owner = co_owner.clone();
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_co_owner")
+        .add_attribute(
+            "co_owner",
+            co_owner.map_or("none".to_string(), |a| a.to_string()),
+        )
+        .add_attribute("method", "try_set_co_owner"))
+}
+
+pub fn try_approve(
+    deps: DepsMut,
+    info: MessageInfo,
+    operator: Addr,
+    expires: Option<u64>,
+) -> Result<Response, ContractError> {
+    let state = load_state(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::NotOwner {});
+    }
+    let approval = Approval {
+        operator: operator.clone(),
+        expires,
+    };
+    APPROVALS.save(deps.storage, &operator, &approval)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "approve")
+        .add_attribute("operator", operator)
+        .add_attribute("method", "try_approve"))
+}
+
+pub fn try_revoke(
+    deps: DepsMut,
+    info: MessageInfo,
+    operator: Addr,
+) -> Result<Response, ContractError> {
+    let state = load_state(deps.storage)?;
+    if info.sender != state.owner {
+        return Err(ContractError::NotOwner {});
+    }
+    APPROVALS.remove(deps.storage, &operator);
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke")
+        .add_attribute("operator", operator)
+        .add_attribute("method", "try_revoke"))
+}
+
+pub fn try_withdraw_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let config = FEE_CONFIG.may_load(deps.storage)?;
+    if config.map(|config| config.admin) != Some(info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let accrued = FEES_ACCRUED.may_load(deps.storage)?.unwrap_or_default();
+    if accrued.is_empty() {
+        return Err(ContractError::NoFeesAccrued {});
+    }
+    FEES_ACCRUED.save(deps.storage, &vec![])?;
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: accrued,
+        })
+        .add_attribute("action", "withdraw_fees")
+        .add_attribute("recipient", recipient)
+        .add_attribute("method", "try_withdraw_fees"))
+}
+
+pub fn try_update_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_admin: Option<String>,
+) -> Result<Response, ContractError> {
+    let new_admin = new_admin
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    update_state(deps.storage, |mut state| {
+        if state.admin.as_ref() != Some(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        state.admin = new_admin.clone();
+        Ok(state)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_admin")
+        .add_attribute(
+            "new_admin",
+            new_admin.map_or_else(|| "none".to_string(), |addr| addr.to_string()),
+        )
+        .add_attribute("method", "try_update_admin"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let from_version = get_contract_version(deps.storage)?.version;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let index = MIGRATIONS
+        .keys(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map_or(0, |last| last + 1);
+    let record = MigrationRecord {
+        from_version,
+        to_version: CONTRACT_VERSION.to_string(),
+        height: env.block.height,
+    };
+    MIGRATIONS.save(deps.storage, index, &record)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", record.from_version)
+        .add_attribute("to_version", record.to_version))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match (msg.id, msg.result) {
+        (CREATOR_CALLBACK_REPLY_ID, SubMsgResult::Err(err)) => Ok(Response::new()
+            .add_attribute("action", "creator_callback_failed")
+            .add_attribute("error", err)),
+        (CREATOR_TRANSFER_HOOK_REPLY_ID, SubMsgResult::Err(err)) => Ok(Response::new()
+            .add_attribute("action", "creator_transfer_hook_failed")
+            .add_attribute("error", err)),
+        (DISTRIBUTION_REPLY_ID, SubMsgResult::Ok(_)) => {
+            PENDING_DISTRIBUTION.remove(deps.storage);
+            Ok(Response::new().add_attribute("action", "distribution_forwarded"))
+        }
+        (DISTRIBUTION_REPLY_ID, SubMsgResult::Err(err)) => {
+            let pending = PENDING_DISTRIBUTION.load(deps.storage)?;
+            PENDING_DISTRIBUTION.remove(deps.storage);
+            Ok(Response::new()
+                .add_message(BankMsg::Send {
+                    to_address: pending.beneficiary.to_string(),
+                    amount: pending.funds,
+                })
+                .add_attribute("action", "distribution_failed")
+                .add_attribute("error", err))
+        }
+        (CREATOR_PAID_REPLY_ID, SubMsgResult::Ok(_)) => {
+            let pending = PENDING_COLLATERAL.load(deps.storage)?;
+            PENDING_COLLATERAL.remove(deps.storage);
+            Ok(Response::new()
+                .add_message(BankMsg::Send {
+                    to_address: pending.beneficiary.to_string(),
+                    amount: pending.funds,
+                })
+                .add_attribute("action", "collateral_released"))
+        }
+        (UNWRAP_REPLY_ID, SubMsgResult::Ok(_)) => {
+            PENDING_UNWRAP.remove(deps.storage);
+            Ok(Response::new().add_attribute("action", "unwrap_forwarded"))
+        }
+        (UNWRAP_REPLY_ID, SubMsgResult::Err(err)) => {
+            let pending = PENDING_UNWRAP.load(deps.storage)?;
+            PENDING_UNWRAP.remove(deps.storage);
+            Ok(Response::new()
+                .add_message(BankMsg::Send {
+                    to_address: pending.beneficiary.to_string(),
+                    amount: pending.funds,
+                })
+                .add_attribute("action", "unwrap_failed")
+                .add_attribute("error", err))
+        }
+        (VESTING_REPLY_ID, SubMsgResult::Ok(_)) => {
+            PENDING_VESTING.remove(deps.storage);
+            Ok(Response::new().add_attribute("action", "vesting_created"))
+        }
+        (VESTING_REPLY_ID, SubMsgResult::Err(err)) => {
+            let pending = PENDING_VESTING.load(deps.storage)?;
+            PENDING_VESTING.remove(deps.storage);
+            Ok(Response::new()
+                .add_message(BankMsg::Send {
+                    to_address: pending.beneficiary.to_string(),
+                    amount: pending.funds,
+                })
+                .add_attribute("action", "vesting_failed")
+                .add_attribute("error", err))
+        }
+        (id, _) => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::ExpectedProceeds {} => to_binary(&query_expected_proceeds(deps)?),
+        QueryMsg::Balances {} => to_binary(&query_balances(deps, env)?),
+        QueryMsg::Solvent {} => to_binary(&query_solvent(deps, env)?),
+        QueryMsg::ExpectedRefund {} => to_binary(&query_expected_refund(deps, env)?),
+        QueryMsg::ConfigV2 {} => to_binary(&query_config_v2(deps)?),
+        QueryMsg::DenomPair {} => to_binary(&query_denom_pair(deps)?),
+        QueryMsg::CreatedAt {} => to_binary(&query_created_at(deps)?),
+        QueryMsg::ExampleMessages {} => to_binary(&query_example_messages(deps)?),
+        QueryMsg::OriginalTerms {} => to_binary(&query_original_terms(deps)?),
+        QueryMsg::WouldAccept { funds } => to_binary(&query_would_accept(deps, funds)?),
+        QueryMsg::Transitions {} => to_binary(&query_transitions(deps, env)?),
+        QueryMsg::NftInfo {} => to_binary(&query_nft_info(deps, env)?),
+        QueryMsg::BookKey {} => to_binary(&query_book_key(deps)?),
+        QueryMsg::ConfigDisplay {} => to_binary(&query_config_display(deps)?),
+        QueryMsg::RequiredPayment {} => to_binary(&query_required_payment(deps)?),
+        QueryMsg::PaymentProgress {} => to_binary(&query_payment_progress(deps)?),
+        QueryMsg::CurrentCounterOffer {} => to_binary(&query_current_counter_offer(deps)?),
+        QueryMsg::FinalAction {} => to_binary(&query_final_action(deps, env)?),
+        QueryMsg::NearExpiry {} => to_binary(&query_near_expiry(deps, env)?),
+        QueryMsg::MigrationHistory { start_after, limit } => {
+            to_binary(&query_migration_history(deps, start_after, limit)?)
+        }
+        QueryMsg::Approvals { start_after, limit } => {
+            to_binary(&query_approvals(deps, env, start_after, limit)?)
+        }
+        QueryMsg::TransferHistory {} => to_binary(&query_transfer_history(deps)?),
+        QueryMsg::BuildInfo {} => to_binary(&query_build_info(deps)?),
+        QueryMsg::LifePercent {} => to_binary(&query_life_percent(deps, env)?),
+        QueryMsg::ConfigAuthenticated { proof } => {
+            to_binary(&query_config_authenticated(deps, proof)?)
+        }
+    }
+}
+
+/// Page size for a paginated query: `DEFAULT_LIMIT` when unset, never above `MAX_LIMIT`.
+/// Every list query must go through this so its work stays bounded.
+fn clamp_limit(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)
+}
+
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(redact_config(state, None))
+}
+
+fn query_config_authenticated(deps: Deps, proof: Addr) -> StdResult<ConfigResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(redact_config(state, Some(&proof)))
+}
+
+/// Hides a private option's parties unless `viewer` is its creator or owner. Queries have
+/// no signer, so the claimed address is taken at its word.
+fn redact_config(mut state: State, viewer: Option<&Addr>) -> State {
+    let revealed = matches!(viewer, Some(v) if *v == state.creator || *v == state.owner);
+    if state.private && !revealed {
+        state.creator = Addr::unchecked(REDACTED);
+        state.owner = Addr::unchecked(REDACTED);
+        if state.pending_creator.is_some() {
+            state.pending_creator = Some(Addr::unchecked(REDACTED));
+        }
+        if state.co_owner.is_some() {
+            state.co_owner = Some(Addr::unchecked(REDACTED));
+        }
+    }
+    state
+}
+
+fn is_private(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(PRIVATE.may_load(storage)?.unwrap_or_default())
+}
+
+fn query_config_display(deps: Deps) -> StdResult<ConfigResponse> {
+    let mut state = redact_config(STATE.load(deps.storage)?, None);
+    let display = |denom: &mut String| -> StdResult<()> {
+        if let Some(name) = DENOM_DISPLAY.may_load(deps.storage, denom)? {
+            *denom = name;
+        }
+        Ok(())
+    };
+    for coin in state
+        .collateral
+        .iter_mut()
+        .chain(state.counter_offer.iter_mut())
+        .chain(state.alternative_counter_offers.iter_mut().flatten())
+    {
+        display(&mut coin.denom)?;
+    }
+    for denom in state.frozen_denoms.iter_mut() {
+        display(denom)?;
+    }
+    Ok(state)
+}
+
+fn query_required_payment(deps: Deps) -> StdResult<RequiredPaymentResponse> {
+    let state = STATE.load(deps.storage)?;
+    let required = if ESCROW.may_load(deps.storage)?.is_some() {
+        vec![]
+    } else {
+        state.counter_offer
+    };
+    Ok(RequiredPaymentResponse { required })
+}
+
+fn query_current_counter_offer(deps: Deps) -> StdResult<CurrentCounterOfferResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(CurrentCounterOfferResponse {
+        counter_offer: state.counter_offer,
+        revision: state.counter_offer_revision,
+    })
+}
+
+fn query_payment_progress(deps: Deps) -> StdResult<PaymentProgressResponse> {
+    let state = STATE.load(deps.storage)?;
+    let remaining = state
+        .counter_offer
+        .iter()
+        .filter_map(|required| {
+            let paid = state
+                .paid_so_far
+                .iter()
+                .find(|c| c.denom == required.denom)
+                .map_or(Uint128::zero(), |c| c.amount);
+            let owed = required.amount.saturating_sub(paid);
+            (!owed.is_zero()).then(|| Coin::new(owed.u128(), required.denom.clone()))
+        })
+        .collect();
+    Ok(PaymentProgressResponse {
+        paid: state.paid_so_far,
+        remaining,
+    })
+}
+
+fn query_config_v2(deps: Deps) -> StdResult<ConfigV2Response> {
+    Ok(ConfigV2Response {
+        schema_version: CONFIG_SCHEMA_VERSION,
+        data: query_config(deps)?,
+    })
+}
+
+fn query_expected_proceeds(deps: Deps) -> StdResult<ProceedsResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(ProceedsResponse {
+        proceeds: execute_fee_split(deps.storage, &state, &state.counter_offer)?.1,
+    })
+}
+
+fn query_expected_refund(deps: Deps, env: Env) -> StdResult<RefundResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(RefundResponse {
+        refund: creator_refund(deps, &env, &state)?,
+    })
+}
+
+fn query_denom_pair(deps: Deps) -> StdResult<DenomPairResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(DenomPairResponse {
+        collateral_denoms: sorted_denoms(&state.collateral),
+        counter_offer_denoms: sorted_denoms(&state.counter_offer),
+    })
+}
+
+fn sorted_denoms(coins: &[Coin]) -> Vec<String> {
+    let mut denoms: Vec<String> = coins.iter().map(|c| c.denom.clone()).collect();
+    denoms.sort();
+    denoms.dedup();
+    denoms
+}
+
+fn query_created_at(deps: Deps) -> StdResult<CreatedAtResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(CreatedAtResponse {
+        created_height: state.created_height,
+    })
+}
+
+// `state` only feeds the transfer example, which is compiled out without `transferable`
+#[cfg_attr(not(feature = "transferable"), allow(unused_variables))]
+fn query_example_messages(deps: Deps) -> StdResult<ExampleMessagesResponse> {
+    let state = redact_config(STATE.load(deps.storage)?, None);
+    Ok(ExampleMessagesResponse {
+        #[cfg(feature = "transferable")]
+        transfer: to_binary(&ExecuteMsg::Transfer {
+            recipient: state.creator,
+        })?,
+        #[cfg(feature = "executable")]
+        execute: to_binary(&ExecuteMsg::Execute {})?,
+        #[cfg(feature = "burnable")]
+        burn: to_binary(&ExecuteMsg::Burn {})?,
+    })
+}
+
+fn query_original_terms(deps: Deps) -> StdResult<OriginalTermsResponse> {
+    let mut terms = ORIGINAL_TERMS.load(deps.storage)?;
+    if is_private(deps.storage)? {
+        terms.creator = Addr::unchecked(REDACTED);
+    }
+    Ok(terms)
+}
+
+fn query_would_accept(deps: Deps, funds: Vec<Coin>) -> StdResult<WouldAcceptResponse> {
+    let state = STATE.load(deps.storage)?;
+    match check_payment(deps.storage, &state, &funds) {
+        Ok(_) => Ok(WouldAcceptResponse {
+            accepted: true,
+            reason: None,
+        }),
+        Err(ContractError::Std(err)) => Err(err),
+        Err(err) => Ok(WouldAcceptResponse {
+            accepted: false,
+            reason: Some(err.to_string()),
+        }),
+    }
+}
+
+fn query_transitions(deps: Deps, env: Env) -> StdResult<TransitionsResponse> {
+    let state = match STATE.may_load(deps.storage)? {
+        Some(state) => state,
+        None => return Ok(TransitionsResponse { actions: vec![] }),
+    };
+    let height = env.block.height;
+    let now = current_time(deps, &state, &env)?;
+    let mut actions = vec![];
+    if !state.is_expired(now) {
+        if cfg!(feature = "transferable") && !state.soulbound {
+            actions.push("transfer");
+        }
+        let available_at = state.created_height.checked_add(state.min_life_blocks);
+        if cfg!(feature = "executable") && matches!(available_at, Some(at) if height >= at) {
+            actions.push("execute");
+        }
+        actions.push("add_collateral");
+    } else {
+        let burn_at = state
+            .expired_at()
+            .checked_add(state.burn_confirmation_blocks);
+        if cfg!(feature = "burnable") && matches!(burn_at, Some(at) if now >= at) {
+            actions.push("burn");
+        }
+        let recover_at = state.expired_at().checked_add(state.recovery_after_blocks);
+        if state.recovery_address.is_some() && matches!(recover_at, Some(at) if now >= at) {
+            actions.push("recover");
+        }
+    }
+    Ok(TransitionsResponse {
+        actions: actions.into_iter().map(String::from).collect(),
+    })
+}
+
+fn query_nft_info(deps: Deps, env: Env) -> StdResult<NftInfoResponse> {
+    let state = STATE.load(deps.storage)?;
+    let collateral = format_coins(&state.collateral);
+    let counter_offer = format_coins(&state.counter_offer);
+    let status = if !state.is_expired(current_time(deps, &state, &env)?) {
+        "active"
+    } else {
+        "expired"
+    };
+    let traits = vec![
+        ("option_type", state.option_type.to_string()),
+        ("expires", state.expires.to_string()),
+        ("collateral", collateral.clone()),
+        ("counter_offer", counter_offer.clone()),
+        ("status", status.to_string()),
+    ];
+    let unit = if state.time_oracle.is_some() {
+        "time"
+    } else {
+        "height"
+    };
+    Ok(NftInfoResponse {
+        name: format!("{} option", state.option_type),
+        description: format!(
+            "Pays {} for {} until {} {}",
+            collateral, counter_offer, unit, state.expires
+        ),
+        attributes: traits
+            .into_iter()
+            .map(|(trait_type, value)| Trait {
+                trait_type: trait_type.to_string(),
+                value,
+            })
+            .collect(),
+    })
+}
+
+/// Renders coins as a comma separated list, e.g. `1BTC,40ETH`.
+fn format_coins(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// uses the original terms so a later creator change does not move the option in the book
+fn query_book_key(deps: Deps) -> StdResult<BookKeyResponse> {
+    let terms = ORIGINAL_TERMS.load(deps.storage)?;
+    let preimage = to_vec(&(terms.creator, terms.created_height))?;
+    let key = Sha256::digest(&preimage)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Ok(BookKeyResponse { key })
+}
+
+/// True while the option is unexpired at `now` but within `warning_window_blocks` of `expires`
+fn is_near_expiry(state: &State, now: u64) -> bool {
+    state.warning_window_blocks > 0
+        && !state.is_expired(now)
+        && state.expires - now <= state.warning_window_blocks
+}
+
+fn query_near_expiry(deps: Deps, env: Env) -> StdResult<NearExpiryResponse> {
+    // settled options have no state left
+    let near_expiry = match STATE.may_load(deps.storage)? {
+        Some(state) => is_near_expiry(&state, current_time(deps, &state, &env)?),
+        None => false,
+    };
+    Ok(NearExpiryResponse { near_expiry })
+}
+
+fn query_migration_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<MigrationHistoryResponse> {
+    let migrations = MIGRATIONS
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(clamp_limit(limit) as usize)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<StdResult<_>>()?;
+    Ok(MigrationHistoryResponse { migrations })
+}
+
+fn query_transfer_history(deps: Deps) -> StdResult<TransferHistoryResponse> {
+    let mut transfers = TRANSFER_HISTORY.may_load(deps.storage)?.unwrap_or_default();
+    if is_private(deps.storage)? {
+        for transfer in transfers.iter_mut() {
+            transfer.from = Addr::unchecked(REDACTED);
+            transfer.to = Addr::unchecked(REDACTED);
+        }
+    }
+    Ok(TransferHistoryResponse { transfers })
+}
+
+fn query_approvals(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ApprovalsResponse> {
+    let start_after = start_after.map(Addr::unchecked);
+    let approvals = APPROVALS
+        .range(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .map(|item| item.map(|(_, approval)| approval))
+        .filter(|item| !matches!(item, Ok(approval) if !is_live_approval(approval, &env)))
+        .take(clamp_limit(limit) as usize)
+        .collect::<StdResult<_>>()?;
+    Ok(ApprovalsResponse { approvals })
+}
+
+fn query_life_percent(deps: Deps, env: Env) -> StdResult<LifePercentResponse> {
+    let state = STATE.load(deps.storage)?;
+    let start = state.created_time.unwrap_or(state.created_height);
+    let total = state.expires.saturating_sub(start);
+    let left = state
+        .expires
+        .saturating_sub(current_time(deps, &state, &env)?);
+    let remaining = if total == 0 {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(left.min(total), total)
+    };
+    Ok(LifePercentResponse { remaining })
+}
+
+fn query_build_info(deps: Deps) -> StdResult<BuildInfoResponse> {
+    let info = get_contract_version(deps.storage)?;
+    Ok(BuildInfoResponse {
+        name: info.contract,
+        version: info.version,
+        git_hash: GIT_HASH.map(String::from),
+    })
+}
+
+fn query_final_action(deps: Deps, env: Env) -> StdResult<FinalActionResponse> {
+    // settled options have no state left
+    let action = match STATE.may_load(deps.storage)? {
+        Some(state) if !state.is_expired(current_time(deps, &state, &env)?) => {
+            Some("execute".to_string())
+        }
+        Some(_) => Some("burn".to_string()),
+        None => None,
+    };
+    Ok(FinalActionResponse { action })
+}
+
+fn query_balances(deps: Deps, env: Env) -> StdResult<BalancesResponse> {
+    let mut balances = deps.querier.query_all_balances(env.contract.address)?;
+    balances.sort_by(|a, b| a.denom.cmp(&b.denom));
+    Ok(BalancesResponse { balances })
+}
+
+fn query_solvent(deps: Deps, env: Env) -> StdResult<SolventResponse> {
+    let state = STATE.load(deps.storage)?;
+    let held = held_collateral(deps, &env, &state, &state.collateral)?;
+    let mut shortfall = vec![];
+    for coin in state.collateral {
+        let held = held
+            .iter()
+            .find(|h| h.denom == coin.denom)
+            .map_or(Uint128::zero(), |h| h.amount);
+        if held < coin.amount {
+            shortfall.push(Coin::new((coin.amount - held).u128(), coin.denom));
+        }
+    }
+    Ok(SolventResponse {
+        solvent: shortfall.is_empty(),
+        shortfall,
+    })
+}
+
+// The main suite exercises every action, so it needs the full default feature set.
+#[cfg(all(
+    test,
+    feature = "transferable",
+    feature = "burnable",
+    feature = "executable"
+))]
+mod tests {
+    use super::*;
+    use crate::state::{DenomAlias, DenomDisplay};
+    use crate::testing::env_at;
+    use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
+    use cosmwasm_std::{coin, coins, from_binary, Attribute, SubMsg};
+
+    fn default_instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            counter_offer: coins(40, "ETH"),
+            expires: Some(100_000),
+            expires_in_blocks: None,
+            option_type: OptionKind::Call,
+            metadata_uri: None,
+            early_exercise_penalty_bps: 0,
+            penalty_until_height: 0,
+            min_life_blocks: 0,
+            admin: None,
+            burn_fee_bps: 0,
+            burn_fee_recipient: None,
+            denom_aliases: vec![],
+            registry: None,
+            burn_actual_balance: false,
+            creator_callback: None,
+            alternative_counter_offers: vec![],
+            recovery_address: None,
+            recovery_after_blocks: 0,
+            settlement_denom: None,
+            execute_fee_bps: 0,
+            fee_admin: None,
+            lot_size: None,
+            topup_extends_expiry: false,
+            extension_per_topup_blocks: 0,
+            denom_display: vec![],
+            oracle: None,
+            max_mispricing_bps: 0,
+            distribution_contract: None,
+            dust_recipient: None,
+            warning_window_blocks: 0,
+            execution_condition: None,
+            min_refund_threshold: None,
+            swap_router: None,
+            desired_proceeds_denom: None,
+            min_swap_output: Uint128::zero(),
+            lapse_to_creator: false,
+            inclusive_expiry: false,
+            max_collateral_denoms: None,
+            max_counter_offer_denoms: None,
+            reject_at_the_money: false,
+            creator_transfer_hook: None,
+            burn_confirmation_blocks: 0,
+            two_phase_settlement: false,
+            unwrap_on_burn: None,
+            accepted_denoms: None,
+            time_oracle: None,
+            history_len: None,
+            private: false,
+            vesting_contract: None,
+        }
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let msg = default_instantiate_msg();
+        let info = mock_info("creator", &coins(1, "BTC"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+        let value: ConfigResponse = from_binary(&res).unwrap();
+
+        assert_eq!(100_000, value.expires);
+        assert_eq!("creator", value.owner);
+        assert_eq!("creator", value.creator);
+        assert_eq!(coins(1, "BTC"), value.collateral);
+        assert_eq!(coins(40, "ETH"), value.counter_offer);
+        assert_eq!(OptionKind::Call, value.option_type);
+    }
+
+    #[test]
+    fn instantiate_from_original_msg() {
+        // InstantiateMsg as sent to the first release of the contract
+        let json = br#"{
+            "expires": 100000,
+            "counter_offer": [{"denom": "ETH", "amount": "40"}]
+        }"#;
+        let msg: InstantiateMsg = cosmwasm_std::from_slice(json).unwrap();
+        assert_eq!(OptionKind::Call, msg.option_type);
+
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(OptionKind::Call, value.option_type);
+    }
+
+    #[test]
+    fn option_type_round_trips() {
+        for option_type in [
+            OptionKind::Call,
+            OptionKind::Put,
+            OptionKind::Other("binary".to_string()),
+        ] {
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+            let msg = InstantiateMsg {
+                option_type: option_type.clone(),
+                ..default_instantiate_msg()
+            };
+            let info = mock_info("creator", &coins(1, "BTC"));
+            let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+            assert_eq!(
+                res.attributes[1],
+                Attribute::new("option_type", option_type.to_string())
+            );
+
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+            let value: ConfigResponse = from_binary(&res).unwrap();
+            assert_eq!(option_type, value.option_type);
+        }
+
+        // an empty custom tag is rejected
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let msg = InstantiateMsg {
+            option_type: OptionKind::Other(" ".to_string()),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::InvalidOptionType {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn transfer() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = default_instantiate_msg();
+        let info = mock_info("creator", &coins(1, "BTC"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+        let _res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+        // random cant transfer
+        let info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("anyone"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::NotOwner {} => {}
+            _ => panic!("Must return not owner error"),
+        }
+
+        // owner can transfer
+        let _info = mock_info("creator", &[]);
+        let _msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("someone"),
+        };
+        let success = execute(deps.as_mut(), mock_env(), _info, _msg).unwrap();
+        assert_eq!(
+            success.attributes,
+            vec![
+                Attribute::new("action", "transfer"),
+                Attribute::new("prev_owner", "creator"),
+                Attribute::new("new_owner", "someone"),
+                // legacy keys, kept for one release
+                Attribute::new("method", "try_transfer"),
+                Attribute::new("new owner", "someone"),
+            ]
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+
+        assert_eq!("someone", value.owner);
+        assert_eq!("creator", value.creator);
+    }
+
+    #[test]
+    fn execute_test() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let counter_offer = coins(40, "ETH");
+        let msg = default_instantiate_msg();
+        let collateral = coins(1, "BTC");
+        let info = mock_info("creator", &collateral);
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        assert_eq!(0, res.messages.len());
+        let _res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+        // random cant execute
+        let info = mock_info("anyone", &counter_offer);
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("anyone"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::NotOwner {} => {}
+            _ => panic!("Must return not owner error"),
+        }
+
+        // expired cannot execute
+        let _info = mock_info("creator", &counter_offer);
+        let _msg = ExecuteMsg::Execute {};
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let _err = execute(deps.as_mut(), env, _info, _msg).unwrap_err();
+        match _err {
+            ContractError::Expired {} => {}
             _ => panic!("Must return unauthorized error"),
         }
 
-        // expired cannot execute
-        let _info = mock_info("creator", &counter_offer);
-        let _msg = ExecuteMsg::Execute {};
+        // wrong counter_offer cannot execute
+        let _info = mock_info("creator", &coins(39, "ADA"));
+        let _msg = ExecuteMsg::Execute {};
+        let _err = execute(deps.as_mut(), mock_env(), _info, _msg).unwrap_err();
+        match _err {
+            ContractError::DiffCounterOffer { counter_offer } => assert_eq!(
+                format!("Must send exact counter_offer: {}", counter_offer),
+                "Must send exact counter_offer: [Coin { denom: \"ETH\", amount: Uint128(40) }]"
+            ),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // proper execution
+        let _info = mock_info("creator", &counter_offer);
+        let _msg = ExecuteMsg::Execute {};
+        let success = execute(deps.as_mut(), mock_env(), _info, _msg).unwrap();
+        assert_eq!(success.messages.len(), 2);
+        assert_eq!(
+            success.messages[0],
+            SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: counter_offer.clone(),
+            })
+        );
+        assert_eq!(
+            success.messages[1],
+            SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: collateral.clone(),
+            })
+        );
+
+        // check deleted
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn burn() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = default_instantiate_msg();
+        let collateral = coins(1, "BTC");
+        let info = mock_info("creator", &collateral);
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        assert_eq!(0, res.messages.len());
+        let _res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+
+        // non-expired cannot burn
+        let _info = mock_info("creator", &[]);
+        let _msg = ExecuteMsg::Burn {};
+        let _err = execute(deps.as_mut(), mock_env(), _info, _msg).unwrap_err();
+        match _err {
+            ContractError::CustomError { val } => assert_eq!("Option not yet expired", val),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // do not send funds to burn
+        let _info = mock_info("creator", &coins(136, "ADA"));
+        let _msg = ExecuteMsg::Burn {};
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let _err = execute(deps.as_mut(), env, _info, _msg).unwrap_err();
+        match _err {
+            ContractError::CustomError { val } => assert_eq!("dont send funds with burn", val),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // return funds if expired
+        let _info = mock_info("creator", &[]);
+        let _msg = ExecuteMsg::Burn {};
+        let mut _env = mock_env();
+        _env.block.height = 200_000;
+        let success = execute(deps.as_mut(), _env, _info, _msg).unwrap();
+        assert_eq!(success.messages.len(), 1);
+        assert_eq!(success.attributes.len(), 2);
+        assert_eq!(success.attributes[0], Attribute::new("action", "burn"));
+        assert_eq!(
+            success.messages[0],
+            SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: collateral.clone(),
+            })
+        );
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn burn_without_collateral() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &[]);
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[0], Attribute::new("action", "burn"));
+        assert_eq!(res.attributes[1], Attribute::new("no_collateral", "true"));
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn burn_confirmation_blocks() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            burn_confirmation_blocks: 10,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        for msg in [ExecuteMsg::Burn {}, ExecuteMsg::BurnAvailable {}] {
+            let info = mock_info("creator", &[]);
+            match execute(deps.as_mut(), env_at(100_009, 0), info, msg).unwrap_err() {
+                ContractError::BurnTooEarly { available_at } => assert_eq!(100_010, available_at),
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+        let info = mock_info("creator", &[]);
+        execute(deps.as_mut(), env_at(100_010, 0), info, ExecuteMsg::Burn {}).unwrap();
+    }
+
+    #[test]
+    fn burn_available() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            admin: Some(Addr::unchecked("admin")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &[coin(1, "BTC"), coin(5, "ATOM")]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = ExecuteMsg::SetFrozenDenoms {
+            denoms: vec!["ATOM".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let info = mock_info("creator", &[]);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::BurnAvailable {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(1, "BTC"),
+            })]
+        );
+
+        // the frozen denom stays tracked for a later retry
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(5, "ATOM"), value.collateral);
+        let info = mock_info("creator", &[]);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::BurnAvailable {},
+        )
+        .unwrap_err();
+
+        let msg = ExecuteMsg::SetFrozenDenoms { denoms: vec![] };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::BurnAvailable {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(5, "ATOM"),
+            })]
+        );
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn add_collateral() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = default_instantiate_msg();
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // only the creator can top up
+        let info = mock_info("anyone", &coins(1, "BTC"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddCollateral {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NotCreator {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // new denoms are rejected
+        let info = mock_info("creator", &coins(5, "ATOM"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddCollateral {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NewDenomNotAllowed {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // existing denom is merged
+        let info = mock_info("creator", &coins(2, "BTC"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddCollateral {},
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(3, "BTC"), value.collateral);
+    }
+
+    #[test]
+    fn validate_instantiate_failures() {
+        let valid = default_instantiate_msg();
+        validate_instantiate(&valid, 99_999).unwrap();
+
+        // already expired
+        match validate_instantiate(&valid, 100_000).unwrap_err() {
+            ContractError::Expired {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // empty custom option type
+        let msg = InstantiateMsg {
+            option_type: OptionKind::Other("".to_string()),
+            ..valid.clone()
+        };
+        match validate_instantiate(&msg, 1).unwrap_err() {
+            ContractError::InvalidOptionType {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // empty or zero counter offer
+        for counter_offer in [vec![], coins(0, "ETH")] {
+            let msg = InstantiateMsg {
+                counter_offer,
+                ..valid.clone()
+            };
+            match validate_instantiate(&msg, 1).unwrap_err() {
+                ContractError::EmptyCounterOffer {} => {}
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+
+        // repeated denom
+        let msg = InstantiateMsg {
+            counter_offer: vec![coin(1, "ETH"), coin(2, "ETH")],
+            ..valid
+        };
+        match validate_instantiate(&msg, 1).unwrap_err() {
+            ContractError::DuplicateDenom { denom } => assert_eq!("ETH", denom),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn metadata_uri() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            metadata_uri: Some("ipfs://terms-v1".to_string()),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some("ipfs://terms-v1".to_string()), value.metadata_uri);
+
+        // only the creator can update
+        let info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::SetMetadataUri {
+            uri: Some("ipfs://terms-v2".to_string()),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::NotCreator {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::SetMetadataUri {
+            uri: Some("ipfs://terms-v2".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some("ipfs://terms-v2".to_string()), value.metadata_uri);
+
+        // over-length uri is rejected
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::SetMetadataUri {
+            uri: Some("a".repeat(MAX_METADATA_URI_LEN + 1)),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::InvalidMetadataUri {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn expected_proceeds() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ExpectedProceeds {}).unwrap();
+        let value: ProceedsResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(40, "ETH"), value.proceeds);
+    }
+
+    #[test]
+    fn early_exercise_penalty() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            early_exercise_penalty_bps: 1_000,
+            penalty_until_height: 50_000,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(100, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // executing early withholds 10% of the collateral for the creator
+        let info = mock_info("owner", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: vec![coin(40, "ETH"), coin(10, "BTC")],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(90, "BTC"),
+                }),
+            ]
+        );
+
+        // after the penalty window the owner gets everything
+        let msg = InstantiateMsg {
+            early_exercise_penalty_bps: 1_000,
+            penalty_until_height: 50_000,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(100, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 60_000;
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages[1],
+            SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(100, "BTC"),
+            })
+        );
+
+        // penalty above 100% is rejected
+        let msg = InstantiateMsg {
+            early_exercise_penalty_bps: 10_001,
+            ..default_instantiate_msg()
+        };
+        match validate_instantiate(&msg, 1).unwrap_err() {
+            ContractError::InvalidBasisPoints {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn balances() {
+        let mut deps = mock_dependencies_with_balance(&[coin(5, "ETH"), coin(2, "BTC")]);
+
+        let info = mock_info("creator", &coins(2, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Balances {}).unwrap();
+        let value: BalancesResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![coin(2, "BTC"), coin(5, "ETH")], value.balances);
+    }
+
+    #[test]
+    fn creator_change() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        // only the creator can propose
+        let info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::ProposeCreatorChange {
+            new_creator: Addr::unchecked("anyone"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::NotCreator {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ProposeCreatorChange {
+            new_creator: Addr::unchecked("vault"),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // a stranger cannot accept
+        let info = mock_info("anyone", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AcceptCreatorChange {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info("vault", &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AcceptCreatorChange {},
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("vault", value.creator);
+        assert_eq!(None, value.pending_creator);
+
+        // creator-gated actions follow the stored creator
+        let info = mock_info("creator", &coins(1, "BTC"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddCollateral {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NotCreator {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let info = mock_info("vault", &coins(1, "BTC"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddCollateral {},
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn min_life_blocks() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            min_life_blocks: 100,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.height += 99;
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let err = execute(deps.as_mut(), env, info, ExecuteMsg::Execute {}).unwrap_err();
+        match err {
+            ContractError::TooEarly { available_at } => {
+                assert_eq!(mock_env().block.height + 100, available_at)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let mut env = mock_env();
+        env.block.height += 100;
+        let info = mock_info("creator", &coins(40, "ETH"));
+        execute(deps.as_mut(), env, info, ExecuteMsg::Execute {}).unwrap();
+    }
+
+    #[test]
+    fn solvent() {
+        let mut deps = mock_dependencies_with_balance(&coins(1, "BTC"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Solvent {}).unwrap();
+        let value: SolventResponse = from_binary(&res).unwrap();
+        assert!(value.solvent);
+        assert!(value.shortfall.is_empty());
+
+        // record more collateral than the contract actually holds
+        let info = mock_info("creator", &coins(2, "BTC"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddCollateral {},
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Solvent {}).unwrap();
+        let value: SolventResponse = from_binary(&res).unwrap();
+        assert!(!value.solvent);
+        assert_eq!(coins(2, "BTC"), value.shortfall);
+    }
+
+    #[test]
+    fn solvent_excludes_funds_held_for_others() {
+        let mut deps = mock_dependencies_with_balance(&coins(1, "BTC"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        // the only BTC held now pays a reward, so none of it backs the collateral
+        let info = mock_info("sponsor", &coins(1, "BTC"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddExecutorReward {},
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Solvent {}).unwrap();
+        let value: SolventResponse = from_binary(&res).unwrap();
+        assert!(!value.solvent);
+        assert_eq!(coins(1, "BTC"), value.shortfall);
+    }
+
+    #[test]
+    fn abort_without_collateral() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            admin: Some(Addr::unchecked("admin")),
+            ..default_instantiate_msg()
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetFrozenDenoms {
+            denoms: vec!["ETH".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::AbortDueToFrozenDenom {
+            denom: "ETH".to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            res.attributes[0],
+            Attribute::new("action", "abort_due_to_frozen_denom")
+        );
+        assert_eq!(res.attributes[2], Attribute::new("no_collateral", "true"));
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn abort_due_to_frozen_denom() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            admin: Some(Addr::unchecked("admin")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // nothing is frozen yet
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::AbortDueToFrozenDenom {
+            denom: "ETH".to_string(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::DenomNotFrozen { denom } => assert_eq!("ETH", denom),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // only the admin maintains the list
+        let msg = ExecuteMsg::SetFrozenDenoms {
+            denoms: vec!["ETH".to_string()],
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+        // strangers cannot abort
+        let msg = ExecuteMsg::AbortDueToFrozenDenom {
+            denom: "ETH".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(1, "BTC"),
+            })]
+        );
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn expected_refund() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ExpectedRefund {}).unwrap();
+        let value: RefundResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(1, "BTC"), value.refund);
+    }
+
+    #[test]
+    fn burn_fee() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let mut env = mock_env();
+        env.block.height = 200_000;
+
+        // no fee by default
+        let info = mock_info("creator", &coins(100, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(100, "BTC"),
+            })]
+        );
+
+        let msg = InstantiateMsg {
+            burn_fee_bps: 250,
+            burn_fee_recipient: Some(Addr::unchecked("treasury")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(100, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ExpectedRefund {}).unwrap();
+        let value: RefundResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(98, "BTC"), value.refund);
+
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(98, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".into(),
+                    amount: coins(2, "BTC"),
+                }),
+            ]
+        );
+
+        // fee bounds
+        let msg = InstantiateMsg {
+            burn_fee_bps: 10_001,
+            burn_fee_recipient: Some(Addr::unchecked("treasury")),
+            ..default_instantiate_msg()
+        };
+        match validate_instantiate(&msg, 1).unwrap_err() {
+            ContractError::InvalidBasisPoints {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let msg = InstantiateMsg {
+            burn_fee_bps: 100,
+            ..default_instantiate_msg()
+        };
+        match validate_instantiate(&msg, 1).unwrap_err() {
+            ContractError::MissingFeeRecipient {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn config_v2() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ConfigV2 {}).unwrap();
+        let value: ConfigV2Response = from_binary(&res).unwrap();
+        assert_eq!(CONFIG_SCHEMA_VERSION, value.schema_version);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config, value.data);
+    }
+
+    #[test]
+    fn denom_pair() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::DenomPair {}).unwrap();
+        let value: DenomPairResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["BTC"], value.collateral_denoms);
+        assert_eq!(vec!["ETH"], value.counter_offer_denoms);
+
+        let msg = InstantiateMsg {
+            counter_offer: vec![coin(40, "USDC"), coin(1, "ATOM")],
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &[coin(1, "BTC"), coin(3, "ADA"), coin(2, "BTC")]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::DenomPair {}).unwrap();
+        let value: DenomPairResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["ADA", "BTC"], value.collateral_denoms);
+        assert_eq!(vec!["ATOM", "USDC"], value.counter_offer_denoms);
+    }
+
+    #[test]
+    fn aliased_counter_offer() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            counter_offer: vec![coin(40, "ETH"), coin(5, "ATOM")],
+            denom_aliases: vec![DenomAlias {
+                alias: "ibc/WETH".to_string(),
+                canonical: "ETH".to_string(),
+            }],
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        assert_eq!(
+            coin(40, "ETH"),
+            normalize_coin(deps.as_ref().storage, &coin(40, "ibc/WETH")).unwrap()
+        );
+        assert_eq!(
+            coin(5, "ATOM"),
+            normalize_coin(deps.as_ref().storage, &coin(5, "ATOM")).unwrap()
+        );
+
+        // an unknown denom is still rejected
+        let info = mock_info("creator", &[coin(5, "ATOM"), coin(40, "WETH")]);
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap_err();
+        match err {
+            ContractError::DiffCounterOffer { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the aliased denom is accepted and forwarded as received
+        let funds = vec![coin(5, "ATOM"), coin(40, "ibc/WETH")];
+        let info = mock_info("creator", &funds);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: funds,
+            })
+        );
+    }
+
+    #[test]
+    fn settlement_hash_is_deterministic() {
+        let creator = Addr::unchecked("creator");
+        let owner = Addr::unchecked("owner");
+        let hash =
+            settlement_hash(&creator, &owner, &coins(1, "BTC"), &coins(40, "ETH"), 10).unwrap();
+
+        assert_eq!(32, hash.len());
+        assert_eq!(
+            hash,
+            settlement_hash(&creator, &owner, &coins(1, "BTC"), &coins(40, "ETH"), 10).unwrap()
+        );
+        assert_ne!(
+            hash,
+            settlement_hash(&creator, &owner, &coins(1, "BTC"), &coins(40, "ETH"), 11).unwrap()
+        );
+        assert_ne!(
+            hash,
+            settlement_hash(&owner, &creator, &coins(1, "BTC"), &coins(40, "ETH"), 10).unwrap()
+        );
+
+        // execute emits the digest of its own settlement
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        let expected = settlement_hash(
+            &creator,
+            &creator,
+            &coins(1, "BTC"),
+            &coins(40, "ETH"),
+            mock_env().block.height,
+        )
+        .unwrap();
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("settlement_hash", expected.to_base64())));
+    }
+
+    #[test]
+    fn update_admin() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            admin: Some(Addr::unchecked("admin")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // non-admins are rejected
+        let msg = ExecuteMsg::UpdateAdmin {
+            new_admin: Some("creator".to_string()),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // rotation
+        let msg = ExecuteMsg::UpdateAdmin {
+            new_admin: Some("council".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("council")), value.admin);
+
+        let msg = ExecuteMsg::SetFrozenDenoms {
+            denoms: vec!["ETH".to_string()],
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("council", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+
+        // renouncement leaves admin actions permanently unavailable
+        let msg_renounce = ExecuteMsg::UpdateAdmin { new_admin: None };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("council", &[]),
+            msg_renounce,
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(None, value.admin);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("council", &[]), msg).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn execute_creator_is_owner_overlapping_denoms() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let collateral = vec![coin(1, "BTC"), coin(5, "ETH")];
+        let info = mock_info("creator", &collateral);
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: vec![coin(45, "ETH"), coin(1, "BTC")],
+            })]
+        );
+    }
+
+    #[test]
+    fn created_at() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let mut env = mock_env();
+        env.block.height = 4_242;
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), env, info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::CreatedAt {}).unwrap();
+        let value: CreatedAtResponse = from_binary(&res).unwrap();
+        assert_eq!(4_242, value.created_height);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(4_242, value.created_height);
+    }
+
+    #[test]
+    fn burn_actual_balance() {
+        // the contract only holds 3 of the 5 BTC recorded as collateral
+        let mut deps = mock_dependencies_with_balance(&[coin(3, "BTC"), coin(7, "ATOM")]);
+
+        let msg = InstantiateMsg {
+            burn_actual_balance: true,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(5, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::ExpectedRefund {}).unwrap();
+        let value: RefundResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(3, "BTC"), value.refund);
+
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(3, "BTC"),
+            })]
+        );
+    }
+
+    #[test]
+    fn burn_actual_balance_keeps_escrow_apart() {
+        // 10 BTC of collateral plus a 40 BTC escrowed counter offer in the same denom
+        let mut deps = mock_dependencies_with_balance(&coins(50, "BTC"));
+
+        let msg = InstantiateMsg {
+            burn_actual_balance: true,
+            counter_offer: coins(40, "BTC"),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(10, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("buyer", &coins(40, "BTC"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::EscrowCounterOffer {},
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::ExpectedRefund {}).unwrap();
+        let value: RefundResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(10, "BTC"), value.refund);
+
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::Burn {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(10, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "buyer".into(),
+                    amount: coins(40, "BTC"),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn burn_actual_balance_keeps_installments_apart() {
+        // 10 BTC of collateral plus a 15 BTC installment in the same denom
+        let mut deps = mock_dependencies_with_balance(&coins(25, "BTC"));
+
+        let msg = InstantiateMsg {
+            burn_actual_balance: true,
+            counter_offer: coins(40, "BTC"),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(10, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &coins(15, "BTC"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::Burn {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(10, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(15, "BTC"),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn burn_available_actual_balance_keeps_escrow_apart() {
+        let mut deps = mock_dependencies_with_balance(&[coin(50, "BTC"), coin(5, "ATOM")]);
+
+        let msg = InstantiateMsg {
+            admin: Some(Addr::unchecked("admin")),
+            burn_actual_balance: true,
+            counter_offer: coins(40, "BTC"),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &[coin(10, "BTC"), coin(5, "ATOM")]);
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("buyer", &coins(40, "BTC"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::EscrowCounterOffer {},
+        )
+        .unwrap();
+        let msg = ExecuteMsg::SetFrozenDenoms {
+            denoms: vec!["ATOM".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+        // the escrow stays behind with the frozen collateral
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let info = mock_info("creator", &[]);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::BurnAvailable {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(10, "BTC"),
+            })]
+        );
+
+        let msg = ExecuteMsg::SetFrozenDenoms { denoms: vec![] };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::BurnAvailable {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(5, "ATOM"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "buyer".into(),
+                    amount: coins(40, "BTC"),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn example_messages() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ExampleMessages {}).unwrap();
+        let value: ExampleMessagesResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            ExecuteMsg::Transfer {
+                recipient: Addr::unchecked("creator"),
+            },
+            from_binary(&value.transfer).unwrap()
+        );
+        assert_eq!(
+            ExecuteMsg::Execute {},
+            from_binary::<ExecuteMsg>(&value.execute).unwrap()
+        );
+        assert_eq!(
+            ExecuteMsg::Burn {},
+            from_binary::<ExecuteMsg>(&value.burn).unwrap()
+        );
+    }
+
+    #[test]
+    fn alternative_counter_offers() {
+        let msg = InstantiateMsg {
+            alternative_counter_offers: vec![coins(1_000, "USDC")],
+            ..default_instantiate_msg()
+        };
+
+        // pay with the primary counter offer or the alternative
+        for payment in [coins(40, "ETH"), coins(1_000, "USDC")] {
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+            let info = mock_info("creator", &coins(1, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+
+            let info = mock_info("creator", &payment);
+            let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+            assert_eq!(
+                res.messages[0],
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: payment,
+                })
+            );
+        }
+
+        // anything else matches nothing
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+        let info = mock_info("creator", &coins(999, "USDC"));
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap_err();
+        match err {
+            ContractError::NoMatchingCounterOffer {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // alternatives are validated like the counter offer
+        let msg = InstantiateMsg {
+            alternative_counter_offers: vec![vec![]],
+            ..msg
+        };
+        match validate_instantiate(&msg, 1).unwrap_err() {
+            ContractError::EmptyCounterOffer {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn min_life_blocks_overflow() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let mut env = mock_env();
+        env.block.height = u64::MAX - 10;
+        let msg = InstantiateMsg {
+            expires: Some(u64::MAX),
+            min_life_blocks: 100,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let err = execute(deps.as_mut(), env, info, ExecuteMsg::Execute {}).unwrap_err();
+        match err {
+            ContractError::Overflow {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn original_terms() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let info = mock_info("creator", &coins(4, "BTC"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddCollateral {},
+        )
+        .unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("someone"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::OriginalTerms {}).unwrap();
+        let terms: OriginalTermsResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            OriginalTerms {
+                creator: Addr::unchecked("creator"),
+                collateral: coins(1, "BTC"),
+                counter_offer: coins(40, "ETH"),
+                expires: 100_000,
+                created_height: mock_env().block.height,
+            },
+            terms
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(5, "BTC"), value.collateral);
+        assert_eq!("someone", value.owner);
+    }
+
+    #[test]
+    fn relative_expiry() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            expires: None,
+            expires_in_blocks: Some(500),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(mock_env().block.height + 500, value.expires);
+
+        let msg = InstantiateMsg {
+            expires_in_blocks: Some(500),
+            ..default_instantiate_msg()
+        };
+        match validate_instantiate(&msg, 1).unwrap_err() {
+            ContractError::AmbiguousExpiry {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let msg = InstantiateMsg {
+            expires: None,
+            ..default_instantiate_msg()
+        };
+        match validate_instantiate(&msg, 1).unwrap_err() {
+            ContractError::AmbiguousExpiry {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn recover() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            recovery_address: Some(Addr::unchecked("governance")),
+            recovery_after_blocks: 1_000,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // blocked until the delay past expiry has elapsed
+        let mut env = mock_env();
+        env.block.height = 100_999;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::Recover {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::RecoverTooEarly { available_at } => assert_eq!(101_000, available_at),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        env.block.height = 101_000;
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("anyone", &[]),
+            ExecuteMsg::Recover {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "governance".into(),
+                amount: coins(1, "BTC"),
+            })]
+        );
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn recover_without_collateral() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            recovery_address: Some(Addr::unchecked("governance")),
+            ..default_instantiate_msg()
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("anyone", &[]),
+            ExecuteMsg::Recover {},
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[0], Attribute::new("action", "recover"));
+        assert_eq!(res.attributes[2], Attribute::new("no_collateral", "true"));
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn recover_requires_address_and_unburned_option() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::Recover {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NoRecoveryAddress {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = InstantiateMsg {
+            recovery_address: Some(Addr::unchecked("governance")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("anyone", &[]),
+            ExecuteMsg::Recover {},
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn transitions() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            min_life_blocks: 10,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let transitions = |deps: Deps, height: u64| -> Vec<String> {
+            let mut env = mock_env();
+            env.block.height = height;
+            let res = query(deps, env, QueryMsg::Transitions {}).unwrap();
+            let value: TransitionsResponse = from_binary(&res).unwrap();
+            value.actions
+        };
+
+        let height = mock_env().block.height;
+        assert_eq!(
+            vec!["transfer", "add_collateral"],
+            transitions(deps.as_ref(), height)
+        );
+        assert_eq!(
+            vec!["transfer", "execute", "add_collateral"],
+            transitions(deps.as_ref(), height + 10)
+        );
+        assert_eq!(vec!["burn"], transitions(deps.as_ref(), 100_000));
+
+        let mut env = mock_env();
+        env.block.height = 100_000;
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        assert!(transitions(deps.as_ref(), 100_000).is_empty());
+    }
+
+    #[test]
+    fn settlement_denom() {
+        let msg = InstantiateMsg {
+            counter_offer: coins(1_000, "USDC"),
+            settlement_denom: Some("USDC".to_string()),
+            ..default_instantiate_msg()
+        };
+        validate_instantiate(&msg, 1).unwrap();
+
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let info = mock_info("creator", &[coin(1, "BTC"), coin(5, "ETH")]);
+        instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+
+        let multi = InstantiateMsg {
+            counter_offer: vec![coin(1_000, "USDC"), coin(1, "ETH")],
+            ..msg.clone()
+        };
+        match validate_instantiate(&multi, 1).unwrap_err() {
+            ContractError::InvalidSettlementDenom {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let wrong = InstantiateMsg {
+            counter_offer: coins(1_000, "USDT"),
+            ..msg
+        };
+        match validate_instantiate(&wrong, 1).unwrap_err() {
+            ContractError::InvalidSettlementDenom {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn withdraw_fees() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            execute_fee_bps: 250,
+            fee_admin: Some(Addr::unchecked("treasurer")),
+            ..default_instantiate_msg()
+        };
+
+        // two options settle through the same contract storage, each keeping 2.5%
+        for _ in 0..2 {
+            let info = mock_info("creator", &coins(1, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::ExpectedProceeds {}).unwrap();
+            let value: ProceedsResponse = from_binary(&res).unwrap();
+            assert_eq!(coins(39, "ETH"), value.proceeds);
+
+            let info = mock_info("creator", &coins(40, "ETH"));
+            let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+            assert_eq!(
+                res.messages[0],
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(39, "ETH"),
+                })
+            );
+        }
+
+        let msg = ExecuteMsg::WithdrawFees {
+            recipient: Addr::unchecked("treasury"),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("treasurer", &[]),
+            msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "treasury".into(),
+                amount: coins(2, "ETH"),
+            })]
+        );
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("treasurer", &[]), msg).unwrap_err();
+        match err {
+            ContractError::NoFeesAccrued {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn lot_size() {
+        let msg = InstantiateMsg {
+            counter_offer: vec![coin(3_000, "USDC"), coin(1, "ETH")],
+            lot_size: Some(coin(1_000, "USDC")),
+            ..default_instantiate_msg()
+        };
+        validate_instantiate(&msg, 1).unwrap();
+
+        let off_lot = InstantiateMsg {
+            counter_offer: coins(3_500, "USDC"),
+            ..msg
+        };
+        match validate_instantiate(&off_lot, 1).unwrap_err() {
+            ContractError::NotLotMultiple {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn nft_info() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::NftInfo {}).unwrap();
+        let value: NftInfoResponse = from_binary(&res).unwrap();
+        assert_eq!("call option", value.name);
+        let expires = value
+            .attributes
+            .iter()
+            .find(|t| t.trait_type == "expires")
+            .unwrap();
+        assert_eq!("100000", expires.value);
+        let status = value
+            .attributes
+            .iter()
+            .find(|t| t.trait_type == "status")
+            .unwrap();
+        assert_eq!("active", status.value);
+    }
+
+    #[test]
+    fn zero_duration_expiry() {
+        let msg = InstantiateMsg {
+            expires: None,
+            expires_in_blocks: Some(0),
+            ..default_instantiate_msg()
+        };
+        match validate_instantiate(&msg, 500).unwrap_err() {
+            ContractError::Expired {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let msg = InstantiateMsg {
+            expires: Some(500),
+            ..default_instantiate_msg()
+        };
+        match validate_instantiate(&msg, 500).unwrap_err() {
+            ContractError::Expired {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn book_key() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let book_key = |deps: Deps| -> String {
+            let res = query(deps, mock_env(), QueryMsg::BookKey {}).unwrap();
+            let value: BookKeyResponse = from_binary(&res).unwrap();
+            value.key
+        };
+        let key = book_key(deps.as_ref());
+        assert_eq!(64, key.len());
+        assert_eq!(key, book_key(deps.as_ref()));
+
+        let msg = ExecuteMsg::ProposeCreatorChange {
+            new_creator: Addr::unchecked("heir"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let msg = ExecuteMsg::AcceptCreatorChange {};
+        execute(deps.as_mut(), mock_env(), mock_info("heir", &[]), msg).unwrap();
+        assert_eq!(key, book_key(deps.as_ref()));
+    }
+
+    #[test]
+    fn topup_extends_expiry() {
+        for (flag, expected) in [(true, 100_500), (false, 100_000)] {
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+            let msg = InstantiateMsg {
+                topup_extends_expiry: flag,
+                extension_per_topup_blocks: 500,
+                ..default_instantiate_msg()
+            };
+            let info = mock_info("creator", &coins(1, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+            let info = mock_info("creator", &coins(1, "BTC"));
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                ExecuteMsg::AddCollateral {},
+            )
+            .unwrap();
+
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+            let value: ConfigResponse = from_binary(&res).unwrap();
+            assert_eq!(expected, value.expires);
+        }
+    }
+
+    #[test]
+    fn config_display() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            counter_offer: coins(40, "ibc/ABC"),
+            denom_display: vec![DenomDisplay {
+                denom: "ibc/ABC".to_string(),
+                display: "ATOM".to_string(),
+            }],
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ConfigDisplay {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(40, "ATOM"), value.counter_offer);
+        assert_eq!(coins(1, "BTC"), value.collateral);
+
+        // presentation only, the stored config keeps the raw denom
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(40, "ibc/ABC"), value.counter_offer);
+    }
+
+    #[test]
+    fn escrow_then_execute() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let info = mock_info("buyer", &coins(50, "ETH"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::EscrowCounterOffer {},
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // a second escrow is rejected while one is held
+        let info = mock_info("buyer", &coins(40, "ETH"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::EscrowCounterOffer {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::EscrowExists {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info("owner", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(40, "ETH"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(1, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "buyer".into(),
+                    amount: coins(10, "ETH"),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn escrow_mismatch() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        for funds in [coins(39, "ETH"), coins(40, "USDC")] {
+            let info = mock_info("buyer", &funds);
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                ExecuteMsg::EscrowCounterOffer {},
+            )
+            .unwrap_err();
+            match err {
+                ContractError::DiffCounterOffer { .. } => {}
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+
+        // an unused escrow goes back to the depositor on burn
+        let info = mock_info("buyer", &coins(40, "ETH"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::EscrowCounterOffer {},
+        )
+        .unwrap();
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[1],
+            SubMsg::new(BankMsg::Send {
+                to_address: "buyer".into(),
+                amount: coins(40, "ETH"),
+            })
+        );
+    }
+
+    #[test]
+    fn required_payment() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let required = |deps: Deps| -> Vec<Coin> {
+            let res = query(deps, mock_env(), QueryMsg::RequiredPayment {}).unwrap();
+            let value: RequiredPaymentResponse = from_binary(&res).unwrap();
+            value.required
+        };
+        assert_eq!(coins(40, "ETH"), required(deps.as_ref()));
+
+        let info = mock_info("buyer", &coins(40, "ETH"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::EscrowCounterOffer {},
+        )
+        .unwrap();
+        assert!(required(deps.as_ref()).is_empty());
+    }
+
+    #[test]
+    fn expiry_follows_height_not_time() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let (now, year) = (mock_env().block.time.seconds(), 365 * 24 * 3600);
+        for time in [now, now + year, now + 100 * year] {
+            let env = env_at(99_999, time);
+            let res = query(deps.as_ref(), env.clone(), QueryMsg::Transitions {}).unwrap();
+            let value: TransitionsResponse = from_binary(&res).unwrap();
+            assert!(value.actions.contains(&"execute".to_string()));
+
+            let info = mock_info("creator", &[]);
+            let err = execute(deps.as_mut(), env, info, ExecuteMsg::Burn {}).unwrap_err();
+            match err {
+                ContractError::CustomError { val } => assert_eq!("Option not yet expired", val),
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+
+        // the expiry height alone ends the option, whatever the block time
+        for time in [0, now] {
+            let env = env_at(100_000, time);
+            let info = mock_info("creator", &coins(40, "ETH"));
+            let err = execute(deps.as_mut(), env, info, ExecuteMsg::Execute {}).unwrap_err();
+            match err {
+                ContractError::Expired {} => {}
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn clamp_limit_bounds() {
+        assert_eq!(DEFAULT_LIMIT, clamp_limit(None));
+        assert_eq!(10, clamp_limit(Some(10)));
+        assert_eq!(MAX_LIMIT, clamp_limit(Some(MAX_LIMIT + 1)));
+    }
+
+    #[test]
+    fn pay_installments() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let progress = |deps: Deps| -> PaymentProgressResponse {
+            let res = query(deps, mock_env(), QueryMsg::PaymentProgress {}).unwrap();
+            from_binary(&res).unwrap()
+        };
+
+        let info = mock_info("owner", &coins(15, "ETH"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+        let value = progress(deps.as_ref());
+        assert_eq!(coins(15, "ETH"), value.paid);
+        assert_eq!(coins(25, "ETH"), value.remaining);
+
+        // a plain execute cannot double up on a payment in progress
+        let info = mock_info("owner", &coins(40, "ETH"));
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap_err();
+        match err {
+            ContractError::InstallmentsInProgress {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the second installment overshoots, settling and returning the surplus
+        let info = mock_info("owner", &coins(30, "ETH"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(40, "ETH"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(1, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(5, "ETH"),
+                }),
+            ]
+        );
+        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    }
+
+    #[test]
+    fn no_escrow_while_installments_in_progress() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let info = mock_info("creator", &coins(10, "ETH"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+
+        let info = mock_info("anyone", &coins(40, "ETH"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::EscrowCounterOffer {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::InstallmentsInProgress {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the installments can still be completed
+        let info = mock_info("creator", &coins(30, "ETH"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn unfinished_installments_refunded_on_burn() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::DiffCounterOffer { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info("creator", &coins(10, "ETH"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[1],
+            SubMsg::new(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(10, "ETH"),
+            })
+        );
+    }
+
+    #[test]
+    fn installments_refunded_to_operator_that_paid() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let msg = ExecuteMsg::Approve {
+            operator: Addr::unchecked("operator"),
+            expires: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        for (payer, amount) in [("owner", 5), ("operator", 10)] {
+            let info = mock_info(payer, &coins(amount, "ETH"));
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                ExecuteMsg::PayInstallment {},
+            )
+            .unwrap();
+        }
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("creator", &[]),
+            ExecuteMsg::Burn {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[1..],
+            [
+                SubMsg::new(BankMsg::Send {
+                    to_address: "operator".into(),
+                    amount: coins(10, "ETH"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(5, "ETH"),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn installment_surplus_refunded_to_operator() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let msg = ExecuteMsg::Approve {
+            operator: Addr::unchecked("operator"),
+            expires: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let info = mock_info("owner", &coins(15, "ETH"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+        let info = mock_info("operator", &coins(30, "ETH"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[2],
+            SubMsg::new(BankMsg::Send {
+                to_address: "operator".into(),
+                amount: coins(5, "ETH"),
+            })
+        );
+        assert!(INSTALLMENTS
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn execute_refunds_surplus() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let info = mock_info("owner", &[coin(45, "ETH"), coin(7, "ATOM")]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(40, "ETH"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(1, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: vec![coin(5, "ETH"), coin(7, "ATOM")],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn refund_threshold() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            min_refund_threshold: Some(coin(5, "ETH")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+
+        // 4 ETH of surplus is below the threshold and goes to the creator
+        let info = mock_info("creator", &[coin(44, "ETH"), coin(1, "ATOM")]);
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages[2..],
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(1, "ATOM"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(4, "ETH"),
+                }),
+            ]
+        );
+
+        // 5 ETH reaches the threshold and is refunded
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let info = mock_info("owner", &coins(45, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages[2..],
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(5, "ETH"),
+            })]
+        );
+    }
+
+    #[test]
+    fn exact_execute_has_no_refund() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // short payments are still rejected
+        let info = mock_info("owner", &[coin(39, "ETH"), coin(7, "ATOM")]);
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap_err();
+
+        let info = mock_info("owner", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(2, res.messages.len());
+    }
+
+    #[test]
+    fn life_percent() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            expires: Some(1_000),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), env_at(200, 0), info, msg).unwrap();
+
+        let remaining = |height: u64| -> Decimal {
+            let res = query(deps.as_ref(), env_at(height, 0), QueryMsg::LifePercent {}).unwrap();
+            let value: LifePercentResponse = from_binary(&res).unwrap();
+            value.remaining
+        };
+        assert_eq!(Decimal::one(), remaining(200));
+        assert_eq!(Decimal::percent(50), remaining(600));
+        assert_eq!(Decimal::zero(), remaining(1_000));
+        assert_eq!(Decimal::zero(), remaining(5_000));
+    }
+
+    #[test]
+    fn build_info() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::BuildInfo {}).unwrap();
+        let value: BuildInfoResponse = from_binary(&res).unwrap();
+        assert_eq!("crates.io:simple-option", value.name);
+        assert_eq!(env!("CARGO_PKG_VERSION"), value.version);
+        assert_eq!(option_env!("GIT_HASH").map(String::from), value.git_hash);
+    }
+
+    #[test]
+    fn migration_history() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.0.1").unwrap();
+
+        let res = migrate(deps.as_mut(), env_at(20_000, 0), MigrateMsg {}).unwrap();
+        assert_eq!("0.0.1", res.attributes[1].value);
+        migrate(deps.as_mut(), env_at(30_000, 0), MigrateMsg {}).unwrap();
+
+        let history = |start_after, limit| -> Vec<MigrationRecord> {
+            let msg = QueryMsg::MigrationHistory { start_after, limit };
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            let value: MigrationHistoryResponse = from_binary(&res).unwrap();
+            value.migrations
+        };
+        assert_eq!(
+            vec![
+                MigrationRecord {
+                    from_version: "0.0.1".to_string(),
+                    to_version: CONTRACT_VERSION.to_string(),
+                    height: 20_000,
+                },
+                MigrationRecord {
+                    from_version: CONTRACT_VERSION.to_string(),
+                    to_version: CONTRACT_VERSION.to_string(),
+                    height: 30_000,
+                },
+            ],
+            history(None, None)
+        );
+        assert_eq!(20_000, history(None, Some(1))[0].height);
+        let rest = history(Some(0), None);
+        assert_eq!(1, rest.len());
+        assert_eq!(30_000, rest[0].height);
+    }
+
+    #[test]
+    fn final_action() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let final_action = |deps: Deps, height: u64| -> Option<String> {
+            let mut env = mock_env();
+            env.block.height = height;
+            let res = query(deps, env, QueryMsg::FinalAction {}).unwrap();
+            let value: FinalActionResponse = from_binary(&res).unwrap();
+            value.action
+        };
+        assert_eq!(
+            Some("execute".to_string()),
+            final_action(deps.as_ref(), 99_999)
+        );
+        assert_eq!(
+            Some("burn".to_string()),
+            final_action(deps.as_ref(), 100_000)
+        );
+
+        let info = mock_info("creator", &coins(40, "ETH"));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(None, final_action(deps.as_ref(), 99_999));
+    }
+
+    #[test]
+    fn near_expiry() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            warning_window_blocks: 1_000,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let near_expiry = |deps: Deps, height: u64| -> bool {
+            let mut env = mock_env();
+            env.block.height = height;
+            let res = query(deps, env, QueryMsg::NearExpiry {}).unwrap();
+            let value: NearExpiryResponse = from_binary(&res).unwrap();
+            value.near_expiry
+        };
+        assert!(!near_expiry(deps.as_ref(), 98_999));
+        assert!(near_expiry(deps.as_ref(), 99_000));
+        assert!(near_expiry(deps.as_ref(), 99_999));
+        assert!(!near_expiry(deps.as_ref(), 100_000));
+
+        // outside the window execute carries no warning
+        let mut early = mock_dependencies_with_balance(&coins(2, "token"));
+        let msg = InstantiateMsg {
+            warning_window_blocks: 1_000,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(early.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let res = execute(early.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert!(!res.attributes.iter().any(|a| a.key == "near_expiry"));
+
+        let mut env = mock_env();
+        env.block.height = 99_500;
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::Execute {}).unwrap();
+        assert!(res
+            .attributes
+            .iter()
+            .any(|a| a.key == "near_expiry" && a.value == "true"));
+        assert!(!near_expiry(deps.as_ref(), 99_500));
+    }
+
+    #[test]
+    fn dust_policy() {
+        // a 1/3 burn fee and a 1/3 early exercise penalty on 10 tokens under each policy,
+        // as (burn fee, burn refund, penalty, exercised collateral)
+        let cases = [
+            (None, (3, 7, 3, 7)),
+            (Some(DustPolicy::ToCreator), (3, 7, 4, 6)),
+            (Some(DustPolicy::ToOwner), (3, 7, 3, 7)),
+            (Some(DustPolicy::ToFeeRecipient), (4, 6, 3, 7)),
+        ];
+        for (policy, (fee, refund, penalty, exercised)) in cases {
+            let msg = InstantiateMsg {
+                burn_fee_bps: 3_333,
+                burn_fee_recipient: Some(Addr::unchecked("fees")),
+                early_exercise_penalty_bps: 3_333,
+                penalty_until_height: 100_000,
+                dust_recipient: policy,
+                ..default_instantiate_msg()
+            };
+
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+            let info = mock_info("creator", &coins(10, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+            let mut env = mock_env();
+            env.block.height = 200_000;
+            let info = mock_info("creator", &[]);
+            let res = execute(deps.as_mut(), env, info, ExecuteMsg::Burn {}).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "creator".into(),
+                        amount: coins(refund, "BTC"),
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "fees".into(),
+                        amount: coins(fee, "BTC"),
+                    }),
+                ]
+            );
+            assert_eq!(10, fee + refund);
+
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+            let info = mock_info("creator", &coins(10, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+            let transfer = ExecuteMsg::Transfer {
+                recipient: Addr::unchecked("owner"),
+            };
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("creator", &[]),
+                transfer,
+            )
+            .unwrap();
+            let info = mock_info("owner", &coins(40, "ETH"));
+            let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+            assert_eq!(
+                res.messages,
+                vec![
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "creator".into(),
+                        amount: vec![coin(40, "ETH"), coin(penalty, "BTC")],
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "owner".into(),
+                        amount: coins(exercised, "BTC"),
+                    }),
+                ]
+            );
+            assert_eq!(10, penalty + exercised);
+        }
+    }
+
+    #[test]
+    fn dust_policy_co_owner_split() {
+        // 1/3 of 10 tokens to the co-owner under each policy, as (owner, co-owner)
+        let cases = [
+            (None, (6, 4)),
+            (Some(DustPolicy::ToCreator), (6, 4)),
+            (Some(DustPolicy::ToOwner), (7, 3)),
+            (Some(DustPolicy::ToFeeRecipient), (6, 4)),
+        ];
+        for (policy, (owner, co_owner)) in cases {
+            let msg = InstantiateMsg {
+                dust_recipient: policy,
+                ..default_instantiate_msg()
+            };
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+            let info = mock_info("creator", &coins(10, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+            let msg = ExecuteMsg::SetCoOwner {
+                co_owner: Some(Addr::unchecked("partner")),
+                split_bps: 3_333,
+            };
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            let info = mock_info("creator", &coins(40, "ETH"));
+            let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+            assert_eq!(
+                res.messages[1..],
+                [
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "creator".into(),
+                        amount: coins(owner, "BTC"),
+                    }),
+                    SubMsg::new(BankMsg::Send {
+                        to_address: "partner".into(),
+                        amount: coins(co_owner, "BTC"),
+                    }),
+                ]
+            );
+            assert_eq!(10, owner + co_owner);
+        }
+    }
+
+    #[test]
+    fn transfer_history() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            history_len: Some(2),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let hops = [("creator", "alice"), ("alice", "bob"), ("bob", "carol")];
+        for (i, (from, to)) in hops.iter().enumerate() {
+            let mut env = mock_env();
+            env.block.height += i as u64;
+            let msg = ExecuteMsg::Transfer {
+                recipient: Addr::unchecked(*to),
+            };
+            execute(deps.as_mut(), env, mock_info(from, &[]), msg).unwrap();
+        }
+
+        // only the last two fit
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TransferHistory {}).unwrap();
+        let value: TransferHistoryResponse = from_binary(&res).unwrap();
+        let height = mock_env().block.height;
+        assert_eq!(
+            vec![
+                TransferRecord {
+                    from: Addr::unchecked("alice"),
+                    to: Addr::unchecked("bob"),
+                    height: height + 1,
+                },
+                TransferRecord {
+                    from: Addr::unchecked("bob"),
+                    to: Addr::unchecked("carol"),
+                    height: height + 2,
+                },
+            ],
+            value.transfers
+        );
+    }
+
+    #[test]
+    fn default_transfer_history_len() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let hops = [("creator", "alice"), ("alice", "bob"), ("bob", "carol")];
+        for (i, (from, to)) in hops.iter().enumerate() {
+            let mut env = mock_env();
+            env.block.height += i as u64;
+            let msg = ExecuteMsg::Transfer {
+                recipient: Addr::unchecked(*to),
+            };
+            execute(deps.as_mut(), env, mock_info(from, &[]), msg).unwrap();
+        }
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TransferHistory {}).unwrap();
+        let value: TransferHistoryResponse = from_binary(&res).unwrap();
+        let height = mock_env().block.height;
+        let recorded: Vec<_> = value
+            .transfers
+            .iter()
+            .map(|t| (t.from.as_str(), t.to.as_str(), t.height))
+            .collect();
+        assert_eq!(
+            vec![
+                ("creator", "alice", height),
+                ("alice", "bob", height + 1),
+                ("bob", "carol", height + 2),
+            ],
+            recorded
+        );
+    }
+
+    #[test]
+    fn executor_reward_paid_on_execute() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let info = mock_info("alice", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AddExecutorReward {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::CustomError { val } => assert_eq!("no reward sent", val),
+            e => panic!("unexpected error: {}", e),
+        }
+        for funder in ["alice", "bob", "alice"] {
+            let info = mock_info(funder, &coins(5, "ATOM"));
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                ExecuteMsg::AddExecutorReward {},
+            )
+            .unwrap();
+            assert_eq!(
+                res.attributes[0],
+                Attribute::new("action", "add_executor_reward")
+            );
+        }
+
+        let info = mock_info("owner", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(40, "ETH"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(1, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(15, "ATOM"),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn executor_reward_refunded_on_burn() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        for (funder, amount) in [("alice", 5), ("bob", 7)] {
+            let info = mock_info(funder, &coins(amount, "ATOM"));
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                ExecuteMsg::AddExecutorReward {},
+            )
+            .unwrap();
+        }
+
+        let mut env = mock_env();
+        env.block.height = 200_000;
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::Burn {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(1, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "alice".into(),
+                    amount: coins(5, "ATOM"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "bob".into(),
+                    amount: coins(7, "ATOM"),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn private_config() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            private: true,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let config = |msg| -> ConfigResponse {
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let value = config(QueryMsg::Config {});
+        assert_eq!("redacted", value.creator);
+        assert_eq!("redacted", value.owner);
+        let value = config(QueryMsg::ConfigAuthenticated {
+            proof: Addr::unchecked("anyone"),
+        });
+        assert_eq!("redacted", value.owner);
+
+        for proof in ["creator", "owner"] {
+            let value = config(QueryMsg::ConfigAuthenticated {
+                proof: Addr::unchecked(proof),
+            });
+            assert_eq!("creator", value.creator);
+            assert_eq!("owner", value.owner);
+        }
+    }
+
+    #[test]
+    fn private_option_redacted_everywhere() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            private: true,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let msg = ExecuteMsg::SetCoOwner {
+            co_owner: Some(Addr::unchecked("partner")),
+            split_bps: 5_000,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let msg = ExecuteMsg::ProposeCreatorChange {
+            new_creator: Addr::unchecked("heir"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let redacted = Some(Addr::unchecked("redacted"));
+        for msg in [QueryMsg::Config {}, QueryMsg::ConfigDisplay {}] {
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            let value: ConfigResponse = from_binary(&res).unwrap();
+            assert_eq!(redacted, value.pending_creator);
+            assert_eq!(redacted, value.co_owner);
+        }
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ConfigV2 {}).unwrap();
+        let value: ConfigV2Response = from_binary(&res).unwrap();
+        assert_eq!("redacted", value.data.owner);
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ConfigAuthenticated {
+                proof: Addr::unchecked("owner"),
+            },
+        )
+        .unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(Some(Addr::unchecked("partner")), value.co_owner);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::OriginalTerms {}).unwrap();
+        let terms: OriginalTermsResponse = from_binary(&res).unwrap();
+        assert_eq!("redacted", terms.creator);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ExampleMessages {}).unwrap();
+        let value: ExampleMessagesResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            ExecuteMsg::Transfer {
+                recipient: Addr::unchecked("redacted"),
+            },
+            from_binary(&value.transfer).unwrap()
+        );
+
+        // settling drops the state, but the history stays redacted
+        let info = mock_info("owner", &coins(40, "ETH"));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::TransferHistory {}).unwrap();
+        let value: TransferHistoryResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![TransferRecord {
+                from: Addr::unchecked("redacted"),
+                to: Addr::unchecked("redacted"),
+                height: mock_env().block.height,
+            }],
+            value.transfers
+        );
+    }
+
+    #[test]
+    fn public_config_not_redacted() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!("creator", value.creator);
+        assert_eq!("creator", value.owner);
+    }
+
+    #[test]
+    fn vesting_excludes_other_routes() {
+        let routes = [
+            InstantiateMsg {
+                distribution_contract: Some(Addr::unchecked("distribution")),
+                ..default_instantiate_msg()
+            },
+            InstantiateMsg {
+                swap_router: Some(Addr::unchecked("router")),
+                desired_proceeds_denom: Some("USDC".to_string()),
+                ..default_instantiate_msg()
+            },
+            InstantiateMsg {
+                two_phase_settlement: true,
+                ..default_instantiate_msg()
+            },
+        ];
+        for msg in routes {
+            let msg = InstantiateMsg {
+                vesting_contract: Some(Addr::unchecked("vesting")),
+                ..msg
+            };
+            match validate_instantiate(&msg, 1).unwrap_err() {
+                ContractError::InvalidVestingConfig {} => {}
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn relist_enforces_counter_offer_limits() {
+        let expired = env_at(100_000, 0);
+        let relist = |new_counter_offer| ExecuteMsg::Relist {
+            new_counter_offer,
+            new_expires: 200_000,
+        };
+        let cases = [
+            (
+                InstantiateMsg {
+                    settlement_denom: Some("ETH".to_string()),
+                    ..default_instantiate_msg()
+                },
+                coins(30, "USDC"),
+                ContractError::InvalidSettlementDenom {},
+            ),
+            (
+                InstantiateMsg {
+                    lot_size: Some(coin(10, "ETH")),
+                    ..default_instantiate_msg()
+                },
+                coins(35, "ETH"),
+                ContractError::NotLotMultiple {},
+            ),
+            (
+                InstantiateMsg {
+                    max_counter_offer_denoms: Some(1),
+                    ..default_instantiate_msg()
+                },
+                vec![coin(30, "ETH"), coin(5, "USDC")],
+                ContractError::TooManyDenoms {},
+            ),
+            (
+                InstantiateMsg {
+                    reject_at_the_money: true,
+                    ..default_instantiate_msg()
+                },
+                coins(1, "ETH"),
+                ContractError::AtTheMoneyNotAllowed {},
+            ),
+        ];
+        for (msg, new_counter_offer, expected) in cases {
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+            let info = mock_info("creator", &coins(1, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+            let info = mock_info("creator", &[]);
+            let err = execute(
+                deps.as_mut(),
+                expired.clone(),
+                info,
+                relist(new_counter_offer),
+            )
+            .unwrap_err();
+            assert_eq!(expected.to_string(), err.to_string());
+        }
+    }
+
+    #[test]
+    fn soulbind() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::Soulbind {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NotOwner {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Soulbind {},
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert!(value.soulbound);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Transitions {}).unwrap();
+        let value: TransitionsResponse = from_binary(&res).unwrap();
+        assert_eq!(vec!["execute", "add_collateral"], value.actions);
+
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("someone"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+        match err {
+            ContractError::Soulbound {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // exercising is unaffected
+        let info = mock_info("owner", &coins(40, "ETH"));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+    }
+
+    #[test]
+    fn handlers_after_settle() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        STATE.remove(deps.as_mut().storage);
+
+        let msgs = vec![
+            ExecuteMsg::Transfer {
+                recipient: Addr::unchecked("owner"),
+            },
+            ExecuteMsg::Execute {},
+            ExecuteMsg::PayInstallment {},
+            ExecuteMsg::EscrowCounterOffer {},
+            ExecuteMsg::Burn {},
+            ExecuteMsg::BurnAvailable {},
+            ExecuteMsg::AddCollateral {},
+            ExecuteMsg::SetMetadataUri { uri: None },
+            ExecuteMsg::ProposeCreatorChange {
+                new_creator: Addr::unchecked("someone"),
+            },
+            ExecuteMsg::AcceptCreatorChange {},
+            ExecuteMsg::SetFrozenDenoms { denoms: vec![] },
+            ExecuteMsg::AbortDueToFrozenDenom {
+                denom: "ETH".to_string(),
+            },
+            ExecuteMsg::UpdateAdmin { new_admin: None },
+            ExecuteMsg::Recover {},
+            ExecuteMsg::Soulbind {},
+            ExecuteMsg::Relist {
+                new_counter_offer: coins(40, "ETH"),
+                new_expires: 200_000,
+            },
+            ExecuteMsg::SetCoOwner {
+                co_owner: None,
+                split_bps: 0,
+            },
+            ExecuteMsg::Approve {
+                operator: Addr::unchecked("operator"),
+                expires: None,
+            },
+            ExecuteMsg::Revoke {
+                operator: Addr::unchecked("operator"),
+            },
+            ExecuteMsg::AddExecutorReward {},
+            ExecuteMsg::SetAcceptedDenoms { denoms: None },
+        ];
+        for msg in msgs {
+            let info = mock_info("creator", &coins(40, "ETH"));
+            match execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err() {
+                ContractError::OptionSettled {} => {}
+                e => panic!("unexpected error for {:?}: {}", msg, e),
+            }
+        }
+    }
+
+    #[test]
+    fn approved_operator() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
 
-        let mut env = mock_env();
-        env.block.height = 200_000;
-        let _err = execute(deps.as_mut(), env, _info, _msg).unwrap_err();
-        match _err {
-            ContractError::Expired {} => {}
-            _ => panic!("Must return unauthorized error"),
-        }
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
 
-        // wrong counter_offer cannot execute
-        let _info = mock_info("creator", &coins(39, "ADA"));
-        let _msg = ExecuteMsg::Execute {};
-        let _err = execute(deps.as_mut(), mock_env(), _info, _msg).unwrap_err();
-        match _err {
-            ContractError::DiffCounterOffer { counter_offer } => assert_eq!(
-                format!("Must send exact counter_offer: {}", counter_offer),
-                "Must send exact counter_offer: [Coin { denom: \"ETH\", amount: Uint128(40) }]"
-            ),
+        let approve = |operator: &str, expires: Option<u64>| ExecuteMsg::Approve {
+            operator: Addr::unchecked(operator),
+            expires,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("operator", &[]),
+            approve("operator", None),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NotOwner {} => {}
             e => panic!("unexpected error: {}", e),
         }
+        let info = mock_info("owner", &[]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            approve("operator", None),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            approve("stale", Some(1)),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            approve("revoked", None),
+        )
+        .unwrap();
+        let msg = ExecuteMsg::Revoke {
+            operator: Addr::unchecked("revoked"),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        // proper execution
-        let _info = mock_info("creator", &counter_offer);
-        let _msg = ExecuteMsg::Execute {};
-        let success = execute(deps.as_mut(), mock_env(), _info, _msg).unwrap();
-        assert_eq!(success.messages.len(), 2);
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Approvals {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: ApprovalsResponse = from_binary(&res).unwrap();
         assert_eq!(
-            success.messages[0],
+            vec![Approval {
+                operator: Addr::unchecked("operator"),
+                expires: None,
+            }],
+            value.approvals
+        );
+
+        for operator in ["stale", "revoked"] {
+            let info = mock_info(operator, &coins(40, "ETH"));
+            let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap_err();
+            match err {
+                ContractError::NotOwner {} => {}
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+
+        // the collateral still goes to the owner when an operator executes
+        let info = mock_info("operator", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages[1],
             SubMsg::new(BankMsg::Send {
-                to_address: "creator".into(),
-                amount: counter_offer.clone(),
+                to_address: "owner".into(),
+                amount: coins(1, "BTC"),
             })
         );
+    }
+
+    #[test]
+    fn approvals_paginated() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        for operator in ["a", "b", "c"] {
+            let msg = ExecuteMsg::Approve {
+                operator: Addr::unchecked(operator),
+                expires: None,
+            };
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        }
+
+        let page = |start_after: Option<&str>, limit| -> Vec<String> {
+            let msg = QueryMsg::Approvals {
+                start_after: start_after.map(String::from),
+                limit,
+            };
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            let value: ApprovalsResponse = from_binary(&res).unwrap();
+            value
+                .approvals
+                .into_iter()
+                .map(|approval| approval.operator.into_string())
+                .collect()
+        };
+        assert_eq!(vec!["a", "b"], page(None, Some(2)));
+        assert_eq!(vec!["c"], page(Some("b"), Some(2)));
+        assert_eq!(vec!["a", "b", "c"], page(None, None));
+    }
+
+    #[test]
+    fn operator_transfer_clears_approvals() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Approve {
+            operator: Addr::unchecked("operator"),
+            expires: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), msg).unwrap();
+        assert_eq!(res.attributes[1], Attribute::new("prev_owner", "creator"));
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Approvals {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let value: ApprovalsResponse = from_binary(&res).unwrap();
+        assert!(value.approvals.is_empty());
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("operator"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), msg).unwrap_err();
+    }
+
+    #[test]
+    fn swap_config() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let router = Some(Addr::unchecked("router"));
+        let invalid = [
+            InstantiateMsg {
+                swap_router: router.clone(),
+                ..default_instantiate_msg()
+            },
+            InstantiateMsg {
+                swap_router: router.clone(),
+                desired_proceeds_denom: Some("USD".to_string()),
+                distribution_contract: Some(Addr::unchecked("distribution")),
+                ..default_instantiate_msg()
+            },
+        ];
+        for msg in invalid {
+            let info = mock_info("creator", &coins(1, "BTC"));
+            match instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err() {
+                ContractError::InvalidSwapConfig {} => {}
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+
+        let msg = InstantiateMsg {
+            swap_router: router,
+            desired_proceeds_denom: Some("USD".to_string()),
+            min_swap_output: Uint128::new(75),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
         assert_eq!(
-            success.messages[1],
-            SubMsg::new(BankMsg::Send {
-                to_address: "creator".into(),
-                amount: collateral.clone(),
+            res.messages[0],
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "router".into(),
+                msg: to_binary(&SwapExecuteMsg::Swap {
+                    ask_denom: "USD".to_string(),
+                    min_output: Uint128::new(75),
+                    recipient: Addr::unchecked("creator"),
+                })
+                .unwrap(),
+                funds: coins(40, "ETH"),
             })
         );
+    }
 
-        // check deleted
-        let _ = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap_err();
+    #[test]
+    fn expired_execute_lapses() {
+        for lapse in [false, true] {
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+            let msg = InstantiateMsg {
+                lapse_to_creator: lapse,
+                ..default_instantiate_msg()
+            };
+            let info = mock_info("creator", &coins(1, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+            let msg = ExecuteMsg::Transfer {
+                recipient: Addr::unchecked("owner"),
+            };
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+            let info = mock_info("owner", &coins(40, "ETH"));
+            let res = execute(
+                deps.as_mut(),
+                env_at(100_000, 0),
+                info,
+                ExecuteMsg::Execute {},
+            );
+            if !lapse {
+                match res.unwrap_err() {
+                    ContractError::Expired {} => {}
+                    e => panic!("unexpected error: {}", e),
+                }
+                continue;
+            }
+            let res = res.unwrap();
+            assert_eq!(
+                res.messages,
+                vec![SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(40, "ETH"),
+                })]
+            );
+            assert_eq!(res.attributes[0], Attribute::new("action", "lapse"));
+
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+            let value: ConfigResponse = from_binary(&res).unwrap();
+            assert_eq!("creator", value.owner.as_str());
+            assert_eq!(coins(1, "BTC"), value.collateral);
+        }
     }
 
     #[test]
-    fn burn() {
+    fn inclusive_expiry() {
+        for inclusive in [false, true] {
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+            let msg = InstantiateMsg {
+                inclusive_expiry: inclusive,
+                ..default_instantiate_msg()
+            };
+            let info = mock_info("creator", &coins(1, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+            // burning is only possible once executing no longer is
+            let burn = execute(
+                deps.as_mut(),
+                env_at(100_000, 0),
+                mock_info("creator", &[]),
+                ExecuteMsg::Burn {},
+            );
+            assert_eq!(!inclusive, burn.is_ok());
+            if inclusive {
+                let info = mock_info("creator", &coins(40, "ETH"));
+                execute(
+                    deps.as_mut(),
+                    env_at(100_000, 0),
+                    info,
+                    ExecuteMsg::Execute {},
+                )
+                .unwrap();
+            }
+        }
+
         let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let msg = InstantiateMsg {
+            inclusive_expiry: true,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &coins(40, "ETH"));
+        match execute(
+            deps.as_mut(),
+            env_at(100_001, 0),
+            info,
+            ExecuteMsg::Execute {},
+        ) {
+            Err(ContractError::Expired {}) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
 
-        let counter_offer = coins(40, "ETH");
+    #[test]
+    fn denom_caps() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let two_coins = vec![coin(1, "BTC"), coin(2, "ETH")];
         let msg = InstantiateMsg {
-            counter_offer: counter_offer.clone(),
-            expires: 100_000,
+            counter_offer: two_coins.clone(),
+            max_collateral_denoms: Some(2),
+            max_counter_offer_denoms: Some(1),
+            ..default_instantiate_msg()
         };
-        let collateral = coins(1, "BTC");
-        let info = mock_info("creator", &collateral);
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &coins(1, "BTC"));
+        match instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err() {
+            ContractError::TooManyDenoms {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
 
-        assert_eq!(0, res.messages.len());
-        let _res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let msg = InstantiateMsg {
+            max_collateral_denoms: Some(1),
+            max_counter_offer_denoms: Some(2),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &two_coins);
+        match instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err() {
+            ContractError::TooManyDenoms {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
 
-        // non-expired cannot burn
-        let _info = mock_info("creator", &[]);
-        let _msg = ExecuteMsg::Burn {};
-        let _err = execute(deps.as_mut(), mock_env(), _info, _msg).unwrap_err();
-        match _err {
-            ContractError::CustomError { val } => assert_eq!("Option not yet expired", val),
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn co_owner() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(10, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let set_co_owner = |split_bps| ExecuteMsg::SetCoOwner {
+            co_owner: Some(Addr::unchecked("partner")),
+            split_bps,
+        };
+        let info = mock_info("owner", &[]);
+        match execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            set_co_owner(10_001),
+        )
+        .unwrap_err()
+        {
+            ContractError::InvalidBasisPoints {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            set_co_owner(3_000),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NotOwner {} => {}
             e => panic!("unexpected error: {}", e),
         }
+        execute(deps.as_mut(), mock_env(), info, set_co_owner(3_000)).unwrap();
 
-        // do not send funds to burn
-        let _info = mock_info("creator", &coins(136, "ADA"));
-        let _msg = ExecuteMsg::Burn {};
-        let mut env = mock_env();
-        env.block.height = 200_000;
-        let _err = execute(deps.as_mut(), env, _info, _msg).unwrap_err();
-        match _err {
-            ContractError::CustomError { val } => assert_eq!("dont send funds with burn", val),
+        let info = mock_info("owner", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(40, "ETH"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(7, "BTC"),
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "partner".into(),
+                    amount: coins(3, "BTC"),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_at_the_money() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            reject_at_the_money: true,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(40, "BTC"));
+        match instantiate(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err() {
+            ContractError::AtTheMoneyNotAllowed {} => {}
             e => panic!("unexpected error: {}", e),
         }
 
-        // return funds if expired
-        let _info = mock_info("creator", &[]);
-        let _msg = ExecuteMsg::Burn {};
-        let mut _env = mock_env();
-        _env.block.height = 200_000;
-        let success = execute(deps.as_mut(), _env, _info, _msg).unwrap();
-        assert_eq!(success.messages.len(), 1);
-        assert_eq!(success.attributes.len(), 1);
+        let info = mock_info("creator", &coins(39, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("creator", &coins(40, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+    }
+
+    #[test]
+    fn relist() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, default_instantiate_msg()).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let relist = |new_expires| ExecuteMsg::Relist {
+            new_counter_offer: coins(30, "ETH"),
+            new_expires,
+        };
+        let info = mock_info("creator", &[]);
+        // still active
+        execute(deps.as_mut(), mock_env(), info.clone(), relist(200_000)).unwrap_err();
+        let expired = env_at(100_000, 0);
+        match execute(
+            deps.as_mut(),
+            expired.clone(),
+            mock_info("owner", &[]),
+            relist(200_000),
+        )
+        .unwrap_err()
+        {
+            ContractError::NotCreator {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        match execute(
+            deps.as_mut(),
+            expired.clone(),
+            info.clone(),
+            relist(100_000),
+        )
+        .unwrap_err()
+        {
+            ContractError::Expired {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        execute(deps.as_mut(), expired, info, relist(200_000)).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(1, "BTC"), value.collateral);
+        assert_eq!(coins(30, "ETH"), value.counter_offer);
+        assert_eq!(200_000, value.expires);
+        assert_eq!("creator", value.owner.as_str());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::CurrentCounterOffer {}).unwrap();
+        let value: CurrentCounterOfferResponse = from_binary(&res).unwrap();
+        assert_eq!(coins(30, "ETH"), value.counter_offer);
+        assert_eq!(1, value.revision);
+
+        // relisting again bumps the revision once more
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Relist {
+            new_counter_offer: coins(30, "ETH"),
+            new_expires: 300_000,
+        };
+        execute(deps.as_mut(), env_at(200_000, 0), info, msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::CurrentCounterOffer {}).unwrap();
+        let value: CurrentCounterOfferResponse = from_binary(&res).unwrap();
+        assert_eq!(2, value.revision);
+
+        // the relisted option settles at the new terms
+        let info = mock_info("creator", &coins(30, "ETH"));
+        execute(
+            deps.as_mut(),
+            env_at(250_000, 0),
+            info,
+            ExecuteMsg::Execute {},
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn two_phase_settlement() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            two_phase_settlement: true,
+            distribution_contract: Some(Addr::unchecked("distribution")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        match instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err() {
+            ContractError::InvalidTwoPhaseSettlement {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = InstantiateMsg {
+            two_phase_settlement: true,
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("owner"),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        let info = mock_info("owner", &coins(40, "ETH"));
+        let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
         assert_eq!(
-            success.messages[0],
-            SubMsg::new(BankMsg::Send {
-                to_address: "creator".into(),
-                amount: collateral.clone(),
-            })
+            res.messages,
+            vec![SubMsg::reply_on_success(
+                BankMsg::Send {
+                    to_address: "creator".into(),
+                    amount: coins(40, "ETH"),
+                },
+                CREATOR_PAID_REPLY_ID
+            )]
+        );
+    }
+
+    #[test]
+    fn accepted_denoms() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            admin: Some(Addr::unchecked("admin")),
+            accepted_denoms: Some(vec!["ETH".to_string()]),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::SetAcceptedDenoms {
+            denoms: Some(vec!["USDC".to_string()]),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+        // the payment still matches the stored counter offer exactly
+        let info = mock_info("creator", &coins(40, "ETH"));
+        let err = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap_err();
+        match err {
+            ContractError::DenomNoLongerAccepted { denom } => assert_eq!("ETH", denom),
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = ExecuteMsg::SetAcceptedDenoms { denoms: None };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        let info = mock_info("creator", &coins(40, "ETH"));
+        execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Execute {}).unwrap();
+    }
+
+    #[test]
+    fn would_accept() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let mut msg = default_instantiate_msg();
+        msg.alternative_counter_offers = vec![coins(100, "USDC")];
+        let info = mock_info("creator", &coins(1, "BTC"));
+        instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let would_accept = |funds: Vec<Coin>| -> WouldAcceptResponse {
+            let res = query(deps.as_ref(), mock_env(), QueryMsg::WouldAccept { funds }).unwrap();
+            from_binary(&res).unwrap()
+        };
+
+        let res = would_accept(coins(40, "ETH"));
+        assert!(res.accepted);
+        assert_eq!(None, res.reason);
+
+        let res = would_accept(coins(100, "USDC"));
+        assert!(res.accepted);
+
+        let res = would_accept(coins(39, "ETH"));
+        assert!(!res.accepted);
+        assert_eq!(
+            Some(ContractError::NoMatchingCounterOffer {}.to_string()),
+            res.reason
+        );
+    }
+
+    #[test]
+    fn would_accept_runs_execute_checks() {
+        let instantiated = || {
+            let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+            let msg = InstantiateMsg {
+                admin: Some(Addr::unchecked("admin")),
+                alternative_counter_offers: vec![coins(100, "USDC")],
+                ..default_instantiate_msg()
+            };
+            let info = mock_info("creator", &coins(1, "BTC"));
+            instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+            deps
+        };
+        let reason = |deps: Deps, funds: Vec<Coin>| -> Option<String> {
+            let res = query(deps, mock_env(), QueryMsg::WouldAccept { funds }).unwrap();
+            let value: WouldAcceptResponse = from_binary(&res).unwrap();
+            assert_eq!(value.accepted, value.reason.is_none());
+            value.reason
+        };
+
+        // a denom the admin no longer accepts
+        let mut deps = instantiated();
+        let msg = ExecuteMsg::SetAcceptedDenoms {
+            denoms: Some(vec!["USDC".to_string()]),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        assert_eq!(
+            Some(
+                ContractError::DenomNoLongerAccepted {
+                    denom: "ETH".to_string()
+                }
+                .to_string()
+            ),
+            reason(deps.as_ref(), coins(40, "ETH"))
+        );
+        assert_eq!(None, reason(deps.as_ref(), coins(100, "USDC")));
+
+        // an escrow already covers the counter offer
+        let mut deps = instantiated();
+        let info = mock_info("anyone", &coins(40, "ETH"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::EscrowCounterOffer {},
+        )
+        .unwrap();
+        assert_eq!(
+            Some(ContractError::EscrowExists {}.to_string()),
+            reason(deps.as_ref(), coins(40, "ETH"))
+        );
+
+        // installments are in progress
+        let mut deps = instantiated();
+        let info = mock_info("creator", &coins(10, "ETH"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::PayInstallment {},
+        )
+        .unwrap();
+        assert_eq!(
+            Some(ContractError::InstallmentsInProgress {}.to_string()),
+            reason(deps.as_ref(), coins(40, "ETH"))
+        );
+    }
+}
+
+// Compiled into each stripped-down build to check the disabled actions are really gone.
+#[cfg(test)]
+mod feature_tests {
+    use crate::msg::ExecuteMsg;
+    use cosmwasm_std::from_slice;
+
+    fn parses(json: &[u8]) -> bool {
+        from_slice::<ExecuteMsg>(json).is_ok()
+    }
+
+    #[test]
+    fn actions_follow_features() {
+        assert_eq!(
+            cfg!(feature = "transferable"),
+            parses(br#"{"transfer":{"recipient":"someone"}}"#)
         );
+        assert_eq!(cfg!(feature = "executable"), parses(br#"{"execute":{}}"#));
+        assert_eq!(cfg!(feature = "burnable"), parses(br#"{"burn":{}}"#));
     }
 }
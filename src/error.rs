@@ -9,12 +9,116 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Only the option owner can do this")]
+    NotOwner {},
+
+    #[error("Only the option creator can do this")]
+    NotCreator {},
+
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
 
     #[error("Option expired")]
     Expired {},
 
+    #[error("Exactly one of expires and expires_in_blocks must be set")]
+    AmbiguousExpiry {},
+
     #[error("Must send exact counter_offer: {counter_offer:?}")]
     DiffCounterOffer { counter_offer: String },
+
+    #[error("Funds match none of the accepted counter offers")]
+    NoMatchingCounterOffer {},
+
+    #[error("Option type must not be empty")]
+    InvalidOptionType {},
+
+    #[error("Cannot add a denom that is not already part of the collateral")]
+    NewDenomNotAllowed {},
+
+    #[error("Counter offer must not be empty")]
+    EmptyCounterOffer {},
+
+    #[error("Denom {denom} appears more than once")]
+    DuplicateDenom { denom: String },
+
+    #[error("Metadata URI must be non-empty and at most 512 characters")]
+    InvalidMetadataUri {},
+
+    #[error("Basis points must not exceed 10000")]
+    InvalidBasisPoints {},
+
+    #[error("Option cannot be executed before height {available_at}")]
+    TooEarly { available_at: u64 },
+
+    #[error("Option cannot be burned before {available_at}")]
+    BurnTooEarly { available_at: u64 },
+
+    #[error("Option cannot be recovered before {available_at}")]
+    RecoverTooEarly { available_at: u64 },
+
+    #[error("Denom {denom} is not a frozen counter offer denom")]
+    DenomNotFrozen { denom: String },
+
+    #[error("A fee recipient is required when a fee is configured")]
+    MissingFeeRecipient {},
+
+    #[error("Block height arithmetic overflowed")]
+    Overflow {},
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("No recovery address is configured")]
+    NoRecoveryAddress {},
+
+    #[error("Counter offer must be a single coin of the settlement denom")]
+    InvalidSettlementDenom {},
+
+    #[error("No fees have accrued")]
+    NoFeesAccrued {},
+
+    #[error("Counter offer amount is not a multiple of the lot size")]
+    NotLotMultiple {},
+
+    #[error("A counter offer is already escrowed")]
+    EscrowExists {},
+
+    #[error("Counter offer is too far from the oracle price")]
+    Mispriced {},
+
+    #[error("Counter offer is being paid in installments")]
+    InstallmentsInProgress {},
+
+    #[error("Option is soulbound to its owner")]
+    Soulbound {},
+
+    #[error("Execution condition is not met")]
+    ConditionNotMet {},
+
+    #[error("Option has already been settled")]
+    OptionSettled {},
+
+    #[error("Swap router needs a desired proceeds denom and no distribution contract")]
+    InvalidSwapConfig {},
+
+    #[error("Too many denoms")]
+    TooManyDenoms {},
+
+    #[error("At the money options are not allowed")]
+    AtTheMoneyNotAllowed {},
+
+    #[error("Two-phase settlement pays the creator directly and cannot be routed")]
+    InvalidTwoPhaseSettlement {},
+
+    #[error(
+        "Vesting pays the creator's proceeds itself and cannot be combined with another route"
+    )]
+    InvalidVestingConfig {},
+
+    #[error("A time oracle measures expiry by timestamp and cannot be combined with block-based expiry settings")]
+    InvalidTimeOracleConfig {},
+
+    #[error("Denom {denom} is no longer accepted")]
+    DenomNoLongerAccepted { denom: String },
 }
@@ -0,0 +1,1206 @@
+use cosmwasm_std::testing::mock_env;
+use cosmwasm_std::{
+    coins, from_slice, to_binary, Addr, Binary, Coin, Deps, DepsMut, Empty, Env, Event,
+    MessageInfo, Response, StdError, StdResult, Uint128,
+};
+use cw_multi_test::{App, AppBuilder, Contract, ContractWrapper, Executor};
+use cw_storage_plus::Item;
+
+use crate::contract::{execute, instantiate, query, reply};
+use crate::error::ContractError;
+use crate::msg::{
+    CreatorCallbackMsg, CreatorTransferHookMsg, DistributionExecuteMsg, ExecuteMsg,
+    FinalActionResponse, InstantiateMsg, OraclePriceResponse, OracleQueryMsg, QueryMsg,
+    RegistryExecuteMsg, SwapExecuteMsg, TimeOracleQueryMsg, TimeOracleResponse, UnwrapExecuteMsg,
+    VestingExecuteMsg,
+};
+use crate::state::OptionKind;
+
+const CREATOR: &str = "creator";
+const OWNER: &str = "owner";
+
+fn option_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query).with_reply(reply))
+}
+
+fn mock_app(balances: &[(&str, Vec<Coin>)]) -> App {
+    AppBuilder::new().build(|router, _, storage| {
+        for (addr, coins) in balances {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(*addr), coins.clone())
+                .unwrap();
+        }
+    })
+}
+
+fn option_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        counter_offer: coins(40, "ETH"),
+        expires: Some(mock_env().block.height + 1_000),
+        expires_in_blocks: None,
+        option_type: OptionKind::Call,
+        metadata_uri: None,
+        early_exercise_penalty_bps: 0,
+        penalty_until_height: 0,
+        min_life_blocks: 0,
+        admin: None,
+        burn_fee_bps: 0,
+        burn_fee_recipient: None,
+        denom_aliases: vec![],
+        registry: None,
+        burn_actual_balance: false,
+        creator_callback: None,
+        alternative_counter_offers: vec![],
+        recovery_address: None,
+        recovery_after_blocks: 0,
+        settlement_denom: None,
+        execute_fee_bps: 0,
+        fee_admin: None,
+        lot_size: None,
+        topup_extends_expiry: false,
+        extension_per_topup_blocks: 0,
+        denom_display: vec![],
+        oracle: None,
+        max_mispricing_bps: 0,
+        distribution_contract: None,
+        dust_recipient: None,
+        warning_window_blocks: 0,
+        execution_condition: None,
+        min_refund_threshold: None,
+        swap_router: None,
+        desired_proceeds_denom: None,
+        min_swap_output: Uint128::zero(),
+        lapse_to_creator: false,
+        inclusive_expiry: false,
+        max_collateral_denoms: None,
+        max_counter_offer_denoms: None,
+        reject_at_the_money: false,
+        creator_transfer_hook: None,
+        burn_confirmation_blocks: 0,
+        two_phase_settlement: false,
+        unwrap_on_burn: None,
+        accepted_denoms: None,
+        time_oracle: None,
+        history_len: None,
+        private: false,
+        vesting_contract: None,
+    }
+}
+
+/// Mock contract that stores every `T` message it receives so tests can inspect it, or
+/// rejects everything when instantiated with `true`.
+mod recorder {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::fmt::Debug;
+
+    const RECEIVED: Item<Vec<Binary>> = Item::new("received");
+    const FAIL: Item<bool> = Item::new("fail");
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        fail: bool,
+    ) -> StdResult<Response> {
+        FAIL.save(deps.storage, &fail)?;
+        RECEIVED.save(deps.storage, &vec![])?;
+        Ok(Response::new())
+    }
+
+    pub fn execute<T: Serialize>(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: T,
+    ) -> StdResult<Response> {
+        if FAIL.load(deps.storage)? {
+            return Err(StdError::generic_err("recorder rejected message"));
+        }
+        RECEIVED.update(deps.storage, |mut received| -> StdResult<_> {
+            received.push(to_binary(&msg)?);
+            Ok(received)
+        })?;
+        Ok(Response::new())
+    }
+
+    pub fn query(deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+        to_binary(&RECEIVED.load(deps.storage)?)
+    }
+
+    pub fn contract<T>() -> Box<dyn Contract<Empty>>
+    where
+        T: Serialize + DeserializeOwned + Clone + Debug + 'static,
+    {
+        Box::new(ContractWrapper::new(execute::<T>, instantiate, query))
+    }
+
+    pub fn received(app: &App, addr: &Addr) -> Vec<Binary> {
+        app.wrap().query_wasm_smart(addr, &Empty {}).unwrap()
+    }
+}
+
+/// Mock price oracle quoting the fixed amount it was instantiated with.
+mod oracle {
+    use super::*;
+
+    const PRICE: Item<Uint128> = Item::new("price");
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        price: Uint128,
+    ) -> StdResult<Response> {
+        PRICE.save(deps.storage, &price)?;
+        Ok(Response::new())
+    }
+
+    pub fn execute(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Empty,
+    ) -> StdResult<Response> {
+        Err(StdError::generic_err("oracle takes no messages"))
+    }
+
+    pub fn query(deps: Deps, _env: Env, _msg: OracleQueryMsg) -> StdResult<Binary> {
+        to_binary(&OraclePriceResponse {
+            amount: PRICE.load(deps.storage)?,
+        })
+    }
+
+    pub fn contract() -> Box<dyn Contract<Empty>> {
+        Box::new(ContractWrapper::new(execute, instantiate, query))
+    }
+}
+
+/// Mock condition contract answering any query with the flag it holds; executing it with a
+/// bool replaces the flag.
+mod condition {
+    use super::*;
+
+    const MET: Item<bool> = Item::new("met");
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        met: bool,
+    ) -> StdResult<Response> {
+        MET.save(deps.storage, &met)?;
+        Ok(Response::new())
+    }
+
+    pub fn execute(deps: DepsMut, _env: Env, _info: MessageInfo, met: bool) -> StdResult<Response> {
+        MET.save(deps.storage, &met)?;
+        Ok(Response::new())
+    }
+
+    pub fn query(deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+        to_binary(&MET.load(deps.storage)?)
+    }
+
+    pub fn contract() -> Box<dyn Contract<Empty>> {
+        Box::new(ContractWrapper::new(execute, instantiate, query))
+    }
+}
+
+/// Mock swap router paying out `rate` units of the ask denom per unit offered, from its own
+/// balance, and failing below the requested minimum.
+mod router {
+    use super::*;
+    use cosmwasm_std::BankMsg;
+
+    const RATE: Item<u128> = Item::new("rate");
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        rate: u128,
+    ) -> StdResult<Response> {
+        RATE.save(deps.storage, &rate)?;
+        Ok(Response::new())
+    }
+
+    pub fn execute(
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        msg: SwapExecuteMsg,
+    ) -> StdResult<Response> {
+        let SwapExecuteMsg::Swap {
+            ask_denom,
+            min_output,
+            recipient,
+        } = msg;
+        let offered: u128 = info.funds.iter().map(|c| c.amount.u128()).sum();
+        let output = Uint128::new(offered * RATE.load(deps.storage)?);
+        if output < min_output {
+            return Err(StdError::generic_err("swap output below minimum"));
+        }
+        Ok(Response::new().add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(output.u128(), ask_denom),
+        }))
+    }
+
+    pub fn query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+        Err(StdError::generic_err("router takes no queries"))
+    }
+
+    pub fn contract() -> Box<dyn Contract<Empty>> {
+        Box::new(ContractWrapper::new(execute, instantiate, query))
+    }
+}
+
+/// Mock time oracle reporting the time it holds; executing it with a number replaces the time.
+mod clock {
+    use super::*;
+
+    const TIME: Item<u64> = Item::new("time");
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        time: u64,
+    ) -> StdResult<Response> {
+        TIME.save(deps.storage, &time)?;
+        Ok(Response::new())
+    }
+
+    pub fn execute(deps: DepsMut, _env: Env, _info: MessageInfo, time: u64) -> StdResult<Response> {
+        TIME.save(deps.storage, &time)?;
+        Ok(Response::new())
+    }
+
+    pub fn query(deps: Deps, _env: Env, _msg: TimeOracleQueryMsg) -> StdResult<Binary> {
+        to_binary(&TimeOracleResponse {
+            time: TIME.load(deps.storage)?,
+        })
+    }
+
+    pub fn contract() -> Box<dyn Contract<Empty>> {
+        Box::new(ContractWrapper::new(execute, instantiate, query))
+    }
+}
+
+fn instantiate_recorder(app: &mut App, contract: Box<dyn Contract<Empty>>, fail: bool) -> Addr {
+    let code_id = app.store_code(contract);
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked("deployer"),
+        &fail,
+        &[],
+        "recorder",
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn registers_with_registry() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC"))]);
+    let registry =
+        instantiate_recorder(&mut app, recorder::contract::<RegistryExecuteMsg>(), false);
+
+    let code_id = app.store_code(option_contract());
+    let msg = InstantiateMsg {
+        registry: Some(registry.clone()),
+        ..option_msg()
+    };
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(CREATOR),
+        &msg,
+        &coins(1, "BTC"),
+        "option",
+        None,
+    )
+    .unwrap();
+
+    let received = recorder::received(&app, &registry);
+    assert_eq!(1, received.len());
+    let registration: RegistryExecuteMsg = from_slice(&received[0]).unwrap();
+    assert_eq!(
+        RegistryExecuteMsg::RegisterOption {
+            creator: Addr::unchecked(CREATOR),
+            counter_offer: coins(40, "ETH"),
+            expires: msg.expires.unwrap(),
+        },
+        registration
+    );
+}
+
+#[test]
+fn registry_failure_aborts_instantiate() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC"))]);
+    let registry = instantiate_recorder(&mut app, recorder::contract::<RegistryExecuteMsg>(), true);
+
+    let code_id = app.store_code(option_contract());
+    let msg = InstantiateMsg {
+        registry: Some(registry),
+        ..option_msg()
+    };
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(CREATOR),
+        &msg,
+        &coins(1, "BTC"),
+        "option",
+        None,
+    )
+    .unwrap_err();
+
+    // the collateral never left the creator
+    let balance = app.wrap().query_balance(CREATOR, "BTC").unwrap();
+    assert_eq!(coins(1, "BTC"), vec![balance]);
+}
+
+fn instantiate_option(app: &mut App, msg: &InstantiateMsg, collateral: &[Coin]) -> Addr {
+    let code_id = app.store_code(option_contract());
+    let option = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked(CREATOR),
+            msg,
+            collateral,
+            "option",
+            None,
+        )
+        .unwrap();
+    app.execute_contract(
+        Addr::unchecked(CREATOR),
+        option.clone(),
+        &ExecuteMsg::Transfer {
+            recipient: Addr::unchecked(OWNER),
+        },
+        &[],
+    )
+    .unwrap();
+    option
+}
+
+fn balance(app: &App, addr: &str, denom: &str) -> u128 {
+    app.wrap().query_balance(addr, denom).unwrap().amount.u128()
+}
+
+#[test]
+fn creator_callback_on_execute() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let vault = instantiate_recorder(&mut app, recorder::contract::<CreatorCallbackMsg>(), false);
+
+    let msg = InstantiateMsg {
+        creator_callback: Some(vault.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        option,
+        &ExecuteMsg::Execute {},
+        &coins(40, "ETH"),
+    )
+    .unwrap();
+
+    let received = recorder::received(&app, &vault);
+    assert_eq!(1, received.len());
+    let callback: CreatorCallbackMsg = from_slice(&received[0]).unwrap();
+    assert_eq!(
+        CreatorCallbackMsg::OptionSettled {
+            counter_offer: coins(40, "ETH"),
+        },
+        callback
+    );
+    assert_eq!(40, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn failing_creator_callback_does_not_block_settlement() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let vault = instantiate_recorder(&mut app, recorder::contract::<CreatorCallbackMsg>(), true);
+
+    let msg = InstantiateMsg {
+        creator_callback: Some(vault),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+    let res = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            option,
+            &ExecuteMsg::Execute {},
+            &coins(40, "ETH"),
+        )
+        .unwrap();
+
+    assert!(res.has_event(&Event::new("wasm").add_attribute("action", "creator_callback_failed")));
+    assert_eq!(40, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn oracle_checks_counter_offer_price() {
+    let mut app = mock_app(&[(CREATOR, coins(2, "BTC"))]);
+    let code_id = app.store_code(oracle::contract());
+    let oracle = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked("deployer"),
+            &Uint128::new(40),
+            &[],
+            "oracle",
+            None,
+        )
+        .unwrap();
+
+    let code_id = app.store_code(option_contract());
+    // 41 is within 5% of the oracle's 40
+    let msg = InstantiateMsg {
+        counter_offer: coins(41, "ETH"),
+        oracle: Some(oracle.clone()),
+        max_mispricing_bps: 500,
+        ..option_msg()
+    };
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(CREATOR),
+        &msg,
+        &coins(1, "BTC"),
+        "option",
+        None,
+    )
+    .unwrap();
+
+    let msg = InstantiateMsg {
+        counter_offer: coins(50, "ETH"),
+        ..msg
+    };
+    let err = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked(CREATOR),
+            &msg,
+            &coins(1, "BTC"),
+            "option",
+            None,
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Mispriced {}.to_string(),
+        err.root_cause().to_string()
+    );
+}
+
+#[test]
+fn oracle_checks_alternative_counter_offer_prices() {
+    let mut app = mock_app(&[(CREATOR, coins(2, "BTC"))]);
+    let code_id = app.store_code(oracle::contract());
+    let oracle = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked("deployer"),
+            &Uint128::new(40),
+            &[],
+            "oracle",
+            None,
+        )
+        .unwrap();
+
+    let code_id = app.store_code(option_contract());
+    let msg = InstantiateMsg {
+        alternative_counter_offers: vec![coins(39, "USDC")],
+        oracle: Some(oracle),
+        max_mispricing_bps: 500,
+        ..option_msg()
+    };
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(CREATOR),
+        &msg,
+        &coins(1, "BTC"),
+        "option",
+        None,
+    )
+    .unwrap();
+
+    // a fairly priced primary does not cover a mispriced alternative
+    let msg = InstantiateMsg {
+        alternative_counter_offers: vec![coins(39, "USDC"), coins(50, "USDT")],
+        ..msg
+    };
+    let err = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked(CREATOR),
+            &msg,
+            &coins(1, "BTC"),
+            "option",
+            None,
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Mispriced {}.to_string(),
+        err.root_cause().to_string()
+    );
+}
+
+#[test]
+fn oracle_checks_relisted_counter_offer_price() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC"))]);
+    let code_id = app.store_code(oracle::contract());
+    let oracle = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked("deployer"),
+            &Uint128::new(40),
+            &[],
+            "oracle",
+            None,
+        )
+        .unwrap();
+    let msg = InstantiateMsg {
+        oracle: Some(oracle),
+        max_mispricing_bps: 500,
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+
+    app.update_block(|block| block.height = option_msg().expires.unwrap());
+    let relist = |amount| ExecuteMsg::Relist {
+        new_counter_offer: coins(amount, "ETH"),
+        new_expires: option_msg().expires.unwrap() + 1_000,
+    };
+    let err = app
+        .execute_contract(Addr::unchecked(CREATOR), option.clone(), &relist(50), &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Mispriced {}.to_string(),
+        err.root_cause().to_string()
+    );
+    app.execute_contract(Addr::unchecked(CREATOR), option, &relist(39), &[])
+        .unwrap();
+}
+
+#[test]
+fn settlement_routed_through_distribution_contract() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let distribution = instantiate_recorder(
+        &mut app,
+        recorder::contract::<DistributionExecuteMsg>(),
+        false,
+    );
+    let msg = InstantiateMsg {
+        distribution_contract: Some(distribution.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        option,
+        &ExecuteMsg::Execute {},
+        &coins(40, "ETH"),
+    )
+    .unwrap();
+
+    let received = recorder::received(&app, &distribution);
+    assert_eq!(1, received.len());
+    match from_slice(&received[0]).unwrap() {
+        DistributionExecuteMsg::RecordAndForward { beneficiary, memo } => {
+            assert_eq!(Addr::unchecked(CREATOR), beneficiary);
+            assert!(memo.starts_with("option settlement "));
+        }
+    }
+    assert_eq!(40, balance(&app, distribution.as_str(), "ETH"));
+    assert_eq!(0, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn failing_distribution_pays_creator_directly() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let distribution = instantiate_recorder(
+        &mut app,
+        recorder::contract::<DistributionExecuteMsg>(),
+        true,
+    );
+    let msg = InstantiateMsg {
+        distribution_contract: Some(distribution.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+
+    let res = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            option,
+            &ExecuteMsg::Execute {},
+            &coins(40, "ETH"),
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("action", "distribution_failed")));
+
+    assert_eq!(0, balance(&app, distribution.as_str(), "ETH"));
+    assert_eq!(40, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn proceeds_paid_into_vesting_contract() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let vesting = instantiate_recorder(&mut app, recorder::contract::<VestingExecuteMsg>(), false);
+    let msg = InstantiateMsg {
+        vesting_contract: Some(vesting.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+
+    let res = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            option,
+            &ExecuteMsg::Execute {},
+            &coins(40, "ETH"),
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("action", "vesting_created")));
+
+    let received = recorder::received(&app, &vesting);
+    assert_eq!(
+        vec![VestingExecuteMsg::CreateVesting {
+            beneficiary: Addr::unchecked(CREATOR),
+        }],
+        received
+            .iter()
+            .map(|msg| from_slice(msg).unwrap())
+            .collect::<Vec<VestingExecuteMsg>>()
+    );
+    assert_eq!(40, balance(&app, vesting.as_str(), "ETH"));
+    assert_eq!(0, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn failing_vesting_pays_creator_directly() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let vesting = instantiate_recorder(&mut app, recorder::contract::<VestingExecuteMsg>(), true);
+    let msg = InstantiateMsg {
+        vesting_contract: Some(vesting.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+
+    let res = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            option,
+            &ExecuteMsg::Execute {},
+            &coins(40, "ETH"),
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("action", "vesting_failed")));
+
+    assert_eq!(0, balance(&app, vesting.as_str(), "ETH"));
+    assert_eq!(40, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn execution_gated_by_condition_contract() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let code_id = app.store_code(condition::contract());
+    let condition = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked("deployer"),
+            &false,
+            &[],
+            "condition",
+            None,
+        )
+        .unwrap();
+    let msg = InstantiateMsg {
+        execution_condition: Some((condition.clone(), to_binary(&Empty {}).unwrap())),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            option.clone(),
+            &ExecuteMsg::Execute {},
+            &coins(40, "ETH"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::ConditionNotMet {}.to_string(),
+        err.root_cause().to_string()
+    );
+
+    app.execute_contract(Addr::unchecked("deployer"), condition, &true, &[])
+        .unwrap();
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        option,
+        &ExecuteMsg::Execute {},
+        &coins(40, "ETH"),
+    )
+    .unwrap();
+    assert_eq!(40, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn proceeds_swapped_through_router() {
+    let mut app = mock_app(&[
+        (CREATOR, coins(2, "BTC")),
+        (OWNER, coins(80, "ETH")),
+        ("deployer", coins(1_000, "USD")),
+    ]);
+    let code_id = app.store_code(router::contract());
+    let router = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked("deployer"),
+            &2u128,
+            &coins(1_000, "USD"),
+            "router",
+            None,
+        )
+        .unwrap();
+
+    // the router only returns 80 USD for 40 ETH
+    let msg = InstantiateMsg {
+        swap_router: Some(router.clone()),
+        desired_proceeds_denom: Some("USD".to_string()),
+        min_swap_output: Uint128::new(81),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        option.clone(),
+        &ExecuteMsg::Execute {},
+        &coins(40, "ETH"),
+    )
+    .unwrap_err();
+    assert_eq!(80, balance(&app, OWNER, "ETH"));
+    assert_eq!(1, balance(&app, option.as_str(), "BTC"));
+
+    let msg = InstantiateMsg {
+        min_swap_output: Uint128::new(80),
+        ..msg
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        option,
+        &ExecuteMsg::Execute {},
+        &coins(40, "ETH"),
+    )
+    .unwrap();
+    assert_eq!(80, balance(&app, CREATOR, "USD"));
+    assert_eq!(0, balance(&app, CREATOR, "ETH"));
+    assert_eq!(40, balance(&app, router.as_str(), "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn transfer_notifies_creator_hook() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC"))]);
+    let hook = instantiate_recorder(
+        &mut app,
+        recorder::contract::<CreatorTransferHookMsg>(),
+        false,
+    );
+
+    let msg = InstantiateMsg {
+        creator_transfer_hook: Some(hook.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        option,
+        &ExecuteMsg::Transfer {
+            recipient: Addr::unchecked("buyer"),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let received: Vec<CreatorTransferHookMsg> = recorder::received(&app, &hook)
+        .iter()
+        .map(|msg| from_slice(msg).unwrap())
+        .collect();
+    assert_eq!(
+        vec![
+            CreatorTransferHookMsg::OwnershipTransferred {
+                from: Addr::unchecked(CREATOR),
+                to: Addr::unchecked(OWNER),
+            },
+            CreatorTransferHookMsg::OwnershipTransferred {
+                from: Addr::unchecked(OWNER),
+                to: Addr::unchecked("buyer"),
+            },
+        ],
+        received
+    );
+}
+
+#[test]
+fn failing_transfer_hook_does_not_block_transfer() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC"))]);
+    let hook = instantiate_recorder(
+        &mut app,
+        recorder::contract::<CreatorTransferHookMsg>(),
+        true,
+    );
+
+    let msg = InstantiateMsg {
+        creator_transfer_hook: Some(hook),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+    let res = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            option,
+            &ExecuteMsg::Transfer {
+                recipient: Addr::unchecked("buyer"),
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(
+        res.has_event(&Event::new("wasm").add_attribute("action", "creator_transfer_hook_failed"))
+    );
+}
+
+#[test]
+fn two_phase_settlement_releases_collateral_after_payment() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let msg = InstantiateMsg {
+        two_phase_settlement: true,
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "BTC"));
+
+    let res = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            option.clone(),
+            &ExecuteMsg::Execute {},
+            &coins(40, "ETH"),
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("action", "collateral_released")));
+
+    assert_eq!(40, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+    assert_eq!(0, balance(&app, option.as_str(), "BTC"));
+    assert_eq!(0, balance(&app, option.as_str(), "ETH"));
+}
+
+fn burn_expired(app: &mut App, option: &Addr) -> cw_multi_test::AppResponse {
+    let expires = option_msg().expires.unwrap();
+    app.update_block(|block| block.height = expires);
+    app.execute_contract(
+        Addr::unchecked(CREATOR),
+        option.clone(),
+        &ExecuteMsg::Burn {},
+        &[],
+    )
+    .unwrap()
+}
+
+#[test]
+fn burn_routes_collateral_through_unwrap_contract() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "wBTC"))]);
+    let unwrap = instantiate_recorder(&mut app, recorder::contract::<UnwrapExecuteMsg>(), false);
+    let msg = InstantiateMsg {
+        unwrap_on_burn: Some(unwrap.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "wBTC"));
+
+    burn_expired(&mut app, &option);
+
+    let received = recorder::received(&app, &unwrap);
+    assert_eq!(
+        UnwrapExecuteMsg::Unwrap {
+            recipient: Addr::unchecked(CREATOR),
+        },
+        from_slice(&received[0]).unwrap()
+    );
+    assert_eq!(1, balance(&app, unwrap.as_str(), "wBTC"));
+    assert_eq!(0, balance(&app, CREATOR, "wBTC"));
+}
+
+#[test]
+fn burn_available_routes_collateral_through_unwrap_contract() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "wBTC"))]);
+    let unwrap = instantiate_recorder(&mut app, recorder::contract::<UnwrapExecuteMsg>(), false);
+    let msg = InstantiateMsg {
+        unwrap_on_burn: Some(unwrap.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "wBTC"));
+
+    app.update_block(|block| block.height = option_msg().expires.unwrap());
+    app.execute_contract(
+        Addr::unchecked(CREATOR),
+        option,
+        &ExecuteMsg::BurnAvailable {},
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(1, recorder::received(&app, &unwrap).len());
+    assert_eq!(1, balance(&app, unwrap.as_str(), "wBTC"));
+    assert_eq!(0, balance(&app, CREATOR, "wBTC"));
+}
+
+#[test]
+fn failing_unwrap_returns_collateral_directly() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "wBTC"))]);
+    let unwrap = instantiate_recorder(&mut app, recorder::contract::<UnwrapExecuteMsg>(), true);
+    let msg = InstantiateMsg {
+        unwrap_on_burn: Some(unwrap.clone()),
+        ..option_msg()
+    };
+    let option = instantiate_option(&mut app, &msg, &coins(1, "wBTC"));
+
+    let res = burn_expired(&mut app, &option);
+    assert!(res.has_event(&Event::new("wasm").add_attribute("action", "unwrap_failed")));
+    assert_eq!(0, balance(&app, unwrap.as_str(), "wBTC"));
+    assert_eq!(1, balance(&app, CREATOR, "wBTC"));
+}
+
+const ORACLE_EXPIRES: u64 = 1_700_000_000;
+
+/// Deploys a mock time oracle at `time` and an option expiring at `ORACLE_EXPIRES` by it.
+fn instantiate_timed_option(app: &mut App, time: u64, msg: InstantiateMsg) -> (Addr, Addr) {
+    let code_id = app.store_code(clock::contract());
+    let clock = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked("deployer"),
+            &time,
+            &[],
+            "clock",
+            None,
+        )
+        .unwrap();
+    let msg = InstantiateMsg {
+        expires: Some(ORACLE_EXPIRES),
+        time_oracle: Some(clock.clone()),
+        ..msg
+    };
+    let option = instantiate_option(app, &msg, &coins(1, "BTC"));
+    (clock, option)
+}
+
+#[test]
+fn expiry_measured_against_time_oracle() {
+    let mut app = mock_app(&[(CREATOR, coins(2, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let (clock, option) = instantiate_timed_option(&mut app, ORACLE_EXPIRES - 60, option_msg());
+
+    // far below `expires` as a height, but the oracle says it has passed
+    app.execute_contract(Addr::unchecked("deployer"), clock, &ORACLE_EXPIRES, &[])
+        .unwrap();
+    let err = app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            option.clone(),
+            &ExecuteMsg::Execute {},
+            &coins(40, "ETH"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Expired {}.to_string(),
+        err.root_cause().to_string()
+    );
+    let err = app
+        .execute_contract(
+            Addr::unchecked(CREATOR),
+            option.clone(),
+            &ExecuteMsg::AddCollateral {},
+            &coins(1, "BTC"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Expired {}.to_string(),
+        err.root_cause().to_string()
+    );
+    let res: FinalActionResponse = app
+        .wrap()
+        .query_wasm_smart(&option, &QueryMsg::FinalAction {})
+        .unwrap();
+    assert_eq!(Some("burn".to_string()), res.action);
+
+    app.execute_contract(Addr::unchecked(CREATOR), option, &ExecuteMsg::Burn {}, &[])
+        .unwrap();
+    assert_eq!(2, balance(&app, CREATOR, "BTC"));
+}
+
+#[test]
+fn executes_before_time_oracle_expiry() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC")), (OWNER, coins(40, "ETH"))]);
+    let (_, option) = instantiate_timed_option(&mut app, ORACLE_EXPIRES - 60, option_msg());
+
+    // past `expires` as a height, but not by the oracle
+    app.update_block(|block| block.height = ORACLE_EXPIRES);
+    let res: FinalActionResponse = app
+        .wrap()
+        .query_wasm_smart(&option, &QueryMsg::FinalAction {})
+        .unwrap();
+    assert_eq!(Some("execute".to_string()), res.action);
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        option,
+        &ExecuteMsg::Execute {},
+        &coins(40, "ETH"),
+    )
+    .unwrap();
+    assert_eq!(40, balance(&app, CREATOR, "ETH"));
+    assert_eq!(1, balance(&app, OWNER, "BTC"));
+}
+
+#[test]
+fn recover_after_time_oracle_expiry() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC"))]);
+    let msg = InstantiateMsg {
+        recovery_address: Some(Addr::unchecked("vault")),
+        ..option_msg()
+    };
+    let (clock, option) = instantiate_timed_option(&mut app, ORACLE_EXPIRES - 60, msg);
+
+    let err = app
+        .execute_contract(
+            Addr::unchecked("anyone"),
+            option.clone(),
+            &ExecuteMsg::Recover {},
+            &[],
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ContractError>(),
+        Some(ContractError::RecoverTooEarly { .. })
+    ));
+
+    app.execute_contract(Addr::unchecked("deployer"), clock, &ORACLE_EXPIRES, &[])
+        .unwrap();
+    app.execute_contract(
+        Addr::unchecked("anyone"),
+        option,
+        &ExecuteMsg::Recover {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(1, balance(&app, "vault", "BTC"));
+}
+
+#[test]
+fn relist_after_time_oracle_expiry() {
+    let mut app = mock_app(&[(CREATOR, coins(1, "BTC"))]);
+    let (clock, option) = instantiate_timed_option(&mut app, ORACLE_EXPIRES - 60, option_msg());
+    app.execute_contract(Addr::unchecked("deployer"), clock, &ORACLE_EXPIRES, &[])
+        .unwrap();
+
+    // the new expiry is also measured by the oracle
+    let relist = |new_expires| ExecuteMsg::Relist {
+        new_counter_offer: coins(50, "ETH"),
+        new_expires,
+    };
+    let err = app
+        .execute_contract(
+            Addr::unchecked(CREATOR),
+            option.clone(),
+            &relist(ORACLE_EXPIRES),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Expired {}.to_string(),
+        err.root_cause().to_string()
+    );
+    app.execute_contract(
+        Addr::unchecked(CREATOR),
+        option.clone(),
+        &relist(ORACLE_EXPIRES + 3_600),
+        &[],
+    )
+    .unwrap();
+    let res: FinalActionResponse = app
+        .wrap()
+        .query_wasm_smart(&option, &QueryMsg::FinalAction {})
+        .unwrap();
+    assert_eq!(Some("execute".to_string()), res.action);
+}
+
+#[test]
+fn time_oracle_rejects_past_or_block_based_expiry() {
+    let mut app = mock_app(&[(CREATOR, coins(3, "BTC"))]);
+    let code_id = app.store_code(clock::contract());
+    let clock = app
+        .instantiate_contract(
+            code_id,
+            Addr::unchecked("deployer"),
+            &ORACLE_EXPIRES,
+            &[],
+            "clock",
+            None,
+        )
+        .unwrap();
+    let option_code = app.store_code(option_contract());
+    let msgs = [
+        (
+            InstantiateMsg {
+                expires: Some(ORACLE_EXPIRES),
+                time_oracle: Some(clock.clone()),
+                ..option_msg()
+            },
+            ContractError::Expired {},
+        ),
+        (
+            InstantiateMsg {
+                expires: None,
+                expires_in_blocks: Some(100),
+                time_oracle: Some(clock.clone()),
+                ..option_msg()
+            },
+            ContractError::InvalidTimeOracleConfig {},
+        ),
+        (
+            InstantiateMsg {
+                expires: Some(ORACLE_EXPIRES + 60),
+                burn_confirmation_blocks: 10,
+                time_oracle: Some(clock),
+                ..option_msg()
+            },
+            ContractError::InvalidTimeOracleConfig {},
+        ),
+    ];
+    for (msg, expected) in msgs {
+        let err = app
+            .instantiate_contract(
+                option_code,
+                Addr::unchecked(CREATOR),
+                &msg,
+                &coins(1, "BTC"),
+                "option",
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(expected.to_string(), err.root_cause().to_string());
+    }
+}
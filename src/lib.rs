@@ -1,7 +1,16 @@
 pub mod contract;
 mod error;
 pub mod helpers;
+#[cfg(all(
+    test,
+    feature = "transferable",
+    feature = "burnable",
+    feature = "executable"
+))]
+mod integration_tests;
 pub mod msg;
 pub mod state;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 pub use crate::error::ContractError;
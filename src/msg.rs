@@ -1,28 +1,554 @@
-use cosmwasm_std::{Addr, Coin};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::State;
+use crate::state::{
+    Approval, DenomAlias, DenomDisplay, DustPolicy, MigrationRecord, OptionKind, OriginalTerms,
+    State, TransferRecord,
+};
+
+/// How an `InstantiateMsg` expresses its expiry
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Expiry {
+    AtHeight(u64),
+    InBlocks(u64),
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    pub expires: u64,
+    /// Absolute expiry height; exactly one of this and `expires_in_blocks` must be set
+    pub expires: Option<u64>,
+    /// Expiry as a number of blocks after the instantiate height
+    pub expires_in_blocks: Option<u64>,
     pub counter_offer: Vec<Coin>,
+    #[serde(default)]
+    pub option_type: OptionKind,
+    /// Optional pointer to off-chain terms, e.g. an IPFS or HTTPS URI
+    pub metadata_uri: Option<String>,
+    /// Share of the collateral, in basis points, withheld from the owner and returned to the
+    /// creator when executing before `penalty_until_height`
+    #[serde(default)]
+    pub early_exercise_penalty_bps: u16,
+    #[serde(default)]
+    pub penalty_until_height: u64,
+    /// Number of blocks after creation before the option can be executed
+    #[serde(default)]
+    pub min_life_blocks: u64,
+    /// Address allowed to maintain the frozen and accepted denom lists
+    pub admin: Option<Addr>,
+    /// Share of the collateral, in basis points, paid to `burn_fee_recipient` on burn
+    #[serde(default)]
+    pub burn_fee_bps: u16,
+    pub burn_fee_recipient: Option<Addr>,
+    /// Denoms accepted on execute as equivalent to a canonical counter offer denom
+    #[serde(default)]
+    pub denom_aliases: Vec<DenomAlias>,
+    /// Registry contract notified with `RegistryExecuteMsg::RegisterOption` on instantiate
+    pub registry: Option<Addr>,
+    /// Refund the contract's actual balance of each collateral denom on burn, rather than
+    /// the recorded collateral
+    #[serde(default)]
+    pub burn_actual_balance: bool,
+    /// Contract notified with `CreatorCallbackMsg::OptionSettled` when the option is executed
+    pub creator_callback: Option<Addr>,
+    /// Other payments accepted on execute instead of `counter_offer`
+    #[serde(default)]
+    pub alternative_counter_offers: Vec<Vec<Coin>>,
+    /// Address that may receive the collateral via `Recover` if the option is left unburned
+    pub recovery_address: Option<Addr>,
+    /// Blocks past expiry before `Recover` becomes available
+    #[serde(default)]
+    pub recovery_after_blocks: u64,
+    /// When set, every accepted counter offer must be a single coin of this denom
+    pub settlement_denom: Option<String>,
+    /// Share of the counter offer, in basis points, kept by the contract on execute
+    #[serde(default)]
+    pub execute_fee_bps: u16,
+    /// Address allowed to withdraw accrued execute fees
+    pub fee_admin: Option<Addr>,
+    /// Counter offer amounts in this denom must be an exact multiple of its amount
+    pub lot_size: Option<Coin>,
+    /// Push `expires` back by `extension_per_topup_blocks` on every `AddCollateral`
+    #[serde(default)]
+    pub topup_extends_expiry: bool,
+    #[serde(default)]
+    pub extension_per_topup_blocks: u64,
+    /// Human readable names for denoms, applied by `ConfigDisplay`
+    #[serde(default)]
+    pub denom_display: Vec<DenomDisplay>,
+    /// Price oracle queried with `OracleQueryMsg::Price` to check the counter offer, and any
+    /// alternatives, on instantiate and relist
+    pub oracle: Option<Addr>,
+    /// Largest accepted distance, in basis points, between the counter offer and the oracle price
+    #[serde(default)]
+    pub max_mispricing_bps: u16,
+    /// Contract that receives the creator's proceeds with `DistributionExecuteMsg::RecordAndForward`
+    /// instead of a direct bank send, e.g. for accounting
+    pub distribution_contract: Option<Addr>,
+    /// Who absorbs rounding dust from fee, penalty and co-owner splits; by default each split's
+    /// share is rounded down
+    pub dust_recipient: Option<DustPolicy>,
+    /// Flag the option as near expiry once it is within this many blocks of `expires`;
+    /// 0 never flags it
+    #[serde(default)]
+    pub warning_window_blocks: u64,
+    /// Contract and smart query that must answer `true` before the option can be executed
+    pub execution_condition: Option<(Addr, Binary)>,
+    /// Surplus coins of this denom below this amount go to the creator instead of being refunded
+    pub min_refund_threshold: Option<Coin>,
+    /// Router that swaps the creator's proceeds into `desired_proceeds_denom` with
+    /// `SwapExecuteMsg::Swap`; cannot be combined with `distribution_contract`
+    pub swap_router: Option<Addr>,
+    pub desired_proceeds_denom: Option<String>,
+    /// Least amount of `desired_proceeds_denom` the swap must return, or it reverts the execute
+    #[serde(default)]
+    pub min_swap_output: Uint128,
+    /// An `Execute` after expiry hands the option back to the creator instead of failing
+    #[serde(default)]
+    pub lapse_to_creator: bool,
+    /// Keep the option exercisable in the `expires` block itself instead of expiring at it
+    #[serde(default)]
+    pub inclusive_expiry: bool,
+    /// Most distinct denoms the collateral may hold
+    pub max_collateral_denoms: Option<u32>,
+    /// Most distinct denoms the counter offer, and each alternative, may hold
+    pub max_counter_offer_denoms: Option<u32>,
+    /// Refuse single-coin options whose counter offer amount equals the collateral amount,
+    /// i.e. a 1:1 strike ratio
+    #[serde(default)]
+    pub reject_at_the_money: bool,
+    /// Contract notified with `CreatorTransferHookMsg::OwnershipTransferred` on every transfer
+    pub creator_transfer_hook: Option<Addr>,
+    /// Blocks that must pass after expiry before `Burn`/`BurnAvailable`, so a burn can never
+    /// race a late execute
+    #[serde(default)]
+    pub burn_confirmation_blocks: u64,
+    /// Pay the creator first and release the collateral to the owner only from the reply to
+    /// that payment; cannot be combined with `distribution_contract` or `swap_router`
+    #[serde(default)]
+    pub two_phase_settlement: bool,
+    /// Queries show the creator, owner, co-owner, pending creator and past owners as
+    /// `redacted`; `ConfigAuthenticated` reveals them to a caller proving to be the creator
+    /// or owner. Only hides them from queries: raw contract storage stays publicly readable
+    #[serde(default)]
+    pub private: bool,
+    /// Contract that receives the creator's proceeds with `VestingExecuteMsg::CreateVesting`
+    /// to stream them out, instead of a direct bank send; cannot be combined with
+    /// `distribution_contract`, `swap_router` or `two_phase_settlement`
+    pub vesting_contract: Option<Addr>,
+    /// Contract that `Burn` and `BurnAvailable` send the returned collateral to with
+    /// `UnwrapExecuteMsg::Unwrap`, e.g. to unwrap a wrapped token for the creator
+    pub unwrap_on_burn: Option<Addr>,
+    /// Oracle queried with `TimeOracleQueryMsg::Time`; when set, `expires` is a timestamp
+    /// compared against the time it reports instead of block height everywhere. Cannot be
+    /// combined with `expires_in_blocks` or the block-based offsets from expiry:
+    /// `warning_window_blocks`, `recovery_after_blocks`, `burn_confirmation_blocks` and
+    /// `extension_per_topup_blocks`
+    pub time_oracle: Option<Addr>,
+    /// Denoms the creator may still be paid in at execute time; `None` accepts any
+    pub accepted_denoms: Option<Vec<String>>,
+    /// Most transfers kept by `QueryMsg::TransferHistory`, oldest dropped first; defaults to 10
+    pub history_len: Option<u32>,
+}
+
+impl InstantiateMsg {
+    /// The expiry form this message uses, or `None` if it sets both or neither
+    pub fn expiry(&self) -> Option<Expiry> {
+        match (self.expires, self.expires_in_blocks) {
+            (Some(height), None) => Some(Expiry::AtHeight(height)),
+            (None, Some(blocks)) => Some(Expiry::InBlocks(blocks)),
+            _ => None,
+        }
+    }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    Transfer { recipient: Addr },
+    #[cfg(feature = "transferable")]
+    Transfer {
+        recipient: Addr,
+    },
+    #[cfg(feature = "executable")]
     Execute {},
+    #[cfg(feature = "burnable")]
     Burn {},
+    /// Like `Burn`, but only refunds collateral coins whose denom is not frozen; frozen coins
+    /// stay recorded so they can be burned once unfrozen
+    #[cfg(feature = "burnable")]
+    BurnAvailable {},
+    AddCollateral {},
+    /// Adds the attached funds to a bonus paid to the owner on execute; each funder gets
+    /// their share back if the option ends without being exercised
+    #[cfg(feature = "executable")]
+    AddExecutorReward {},
+    SetMetadataUri {
+        uri: Option<String>,
+    },
+    /// Nominates a new creator, who must then accept with `AcceptCreatorChange`
+    ProposeCreatorChange {
+        new_creator: Addr,
+    },
+    AcceptCreatorChange {},
+    /// Admin only. Replaces the list of denoms considered unusable on this chain
+    SetFrozenDenoms {
+        denoms: Vec<String>,
+    },
+    /// Admin only. Replaces the denoms the creator may be paid in at execute; `None` accepts any
+    SetAcceptedDenoms {
+        denoms: Option<Vec<String>>,
+    },
+    /// Refunds the collateral to the creator when the counter offer uses a frozen denom
+    AbortDueToFrozenDenom {
+        denom: String,
+    },
+    /// Admin only. Hands the admin role to `new_admin`, or renounces it for good when `None`
+    UpdateAdmin {
+        new_admin: Option<String>,
+    },
+    /// Anyone. Sends the collateral to `recovery_address` once the option has been expired
+    /// and unburned for `recovery_after_blocks`
+    Recover {},
+    /// Anyone. Escrows the attached funds, which must cover the counter offer, so the owner
+    /// can later execute without sending funds; any excess is refunded on execute. Rejected
+    /// while installments are in progress
+    #[cfg(feature = "executable")]
+    EscrowCounterOffer {},
+    /// Owner or approved operator. Adds the attached counter offer denoms to `paid_so_far` and
+    /// settles the option as soon as the whole counter offer has been paid; surplus goes back to
+    /// the sender of the final installment, and unsettled installments are refunded to each payer
+    #[cfg(feature = "executable")]
+    PayInstallment {},
+    /// Owner only. Permanently binds the option to its current owner; execute and burn still work
+    Soulbind {},
+    /// Creator only, once expired. Lists the same collateral again with new terms, instead of
+    /// burning and re-instantiating; ownership returns to the creator and alternative counter
+    /// offers are dropped. The new counter offer must pass the instantiate-time checks
+    Relist {
+        new_counter_offer: Vec<Coin>,
+        new_expires: u64,
+    },
+    /// Owner only. Pays `co_owner` `split_bps` of the collateral on execute, with the owner
+    /// keeping the rest; rounding dust goes to the co-owner unless `dust_recipient` is
+    /// `ToOwner`. `None` removes the co-owner
+    SetCoOwner {
+        co_owner: Option<Addr>,
+        split_bps: u16,
+    },
+    /// Owner only. Lets `operator` transfer and execute until block height `expires`, or
+    /// until the next transfer if unset
+    Approve {
+        operator: Addr,
+        expires: Option<u64>,
+    },
+    /// Owner only. Withdraws an approval granted with `Approve`
+    Revoke {
+        operator: Addr,
+    },
+    /// Fee admin only. Sends every accrued execute fee to `recipient`
+    WithdrawFees {
+        recipient: Addr,
+    },
+}
+
+/// Messages this contract sends to an external registry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryExecuteMsg {
+    RegisterOption {
+        creator: Addr,
+        counter_offer: Vec<Coin>,
+        expires: u64,
+    },
+}
+
+/// Query this contract sends to the price oracle
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQueryMsg {
+    /// Fair value of `collateral` expressed in `denom`
+    Price {
+        collateral: Vec<Coin>,
+        denom: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OraclePriceResponse {
+    pub amount: Uint128,
+}
+
+/// Query this contract sends to the time oracle
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeOracleQueryMsg {
+    /// Current external time, in the same unit as `expires`
+    Time {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TimeOracleResponse {
+    pub time: u64,
+}
+
+/// Message this contract sends, with the creator's proceeds attached, to the distribution contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionExecuteMsg {
+    RecordAndForward { beneficiary: Addr, memo: String },
+}
+
+/// Message this contract sends, with the creator's proceeds attached, to the vesting contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VestingExecuteMsg {
+    CreateVesting { beneficiary: Addr },
+}
+
+/// Message this contract sends, with the burned collateral attached, to the unwrap contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnwrapExecuteMsg {
+    Unwrap { recipient: Addr },
+}
+
+/// Message this contract sends, with the proceeds to convert attached, to the swap router
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapExecuteMsg {
+    Swap {
+        ask_denom: String,
+        min_output: Uint128,
+        recipient: Addr,
+    },
+}
+
+/// Messages this contract sends to the creator's callback contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CreatorCallbackMsg {
+    OptionSettled { counter_offer: Vec<Coin> },
+}
+
+/// Messages this contract sends to the creator's transfer hook contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CreatorTransferHookMsg {
+    OwnershipTransferred { from: Addr, to: Addr },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     Config {},
+    /// Coins the creator would receive if the option were executed now
+    ExpectedProceeds {},
+    /// All coins held by this contract, sorted by denom
+    Balances {},
+    /// Whether the contract's bank balance, less escrow, installments, executor rewards and
+    /// accrued fees, covers the recorded collateral
+    Solvent {},
+    /// Coins the creator would get back if the option were burned
+    ExpectedRefund {},
+    /// Same as `Config`, wrapped in an envelope carrying `CONFIG_SCHEMA_VERSION`
+    ConfigV2 {},
+    /// Sorted, deduplicated denoms on each side of the option
+    DenomPair {},
+    /// Block height at which the option was instantiated
+    CreatedAt {},
+    /// Ready-to-send encoded `ExecuteMsg`s for the main actions, built from the current state
+    ExampleMessages {},
+    /// The terms as originally instantiated, unaffected by later changes
+    OriginalTerms {},
+    /// Whether `funds` would be accepted by `Execute`, and why not if rejected
+    WouldAccept {
+        funds: Vec<Coin>,
+    },
+    /// Lifecycle actions anyone could take right now, regardless of sender; empty once settled
+    Transitions {},
+    /// The option described as cw721-style NFT metadata, for marketplace listings
+    NftInfo {},
+    /// Stable hex key for this option derived from its original creator and creation height
+    BookKey {},
+    /// Same as `Config`, with denoms translated through the instantiate-time display names
+    ConfigDisplay {},
+    /// Coins to attach to `Execute`: the counter offer, or nothing once it has been escrowed
+    RequiredPayment {},
+    /// Installments paid so far and what is still owed before the option settles
+    PaymentProgress {},
+    /// The live counter offer and how many times it has been replaced, so clients can detect
+    /// a stale quote before executing
+    CurrentCounterOffer {},
+    /// The action that ends the option now: `execute` while active, `burn` once expired,
+    /// `None` once settled
+    FinalAction {},
+    /// Whether the option is within `warning_window_blocks` of expiry
+    NearExpiry {},
+    /// Migrations applied to this contract, oldest first. Migrations are numbered from 0 and
+    /// the page starts after the one numbered `start_after`
+    MigrationHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Unexpired operator approvals granted by the current owner, ordered by operator and
+    /// starting after `start_after`
+    Approvals {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// The most recent transfers, oldest first, up to `history_len` of them
+    TransferHistory {},
+    /// The stored cw2 contract info plus the git hash the contract was built from, if known
+    BuildInfo {},
+    /// Share of the option's life, from `created_height` to `expires`, still remaining: 1 at
+    /// creation, falling to 0 at expiry and staying there. Meant for progress bars
+    LifePercent {},
+    /// Same as `Config`, revealing a private option's creator and owner when `proof` is one
+    /// of them
+    ConfigAuthenticated {
+        proof: Addr,
+    },
 }
 
 // We define a custom struct for each query response
 pub type ConfigResponse = State;
+pub type OriginalTermsResponse = OriginalTerms;
+
+/// Bumped whenever the shape of `State` changes in a way clients need to know about.
+/// Version 2 covers the fields added since `ConfigV2` was introduced, from `burn_actual_balance`
+/// to `counter_offer_revision`
+pub const CONFIG_SCHEMA_VERSION: u16 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigV2Response {
+    pub schema_version: u16,
+    pub data: State,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProceedsResponse {
+    pub proceeds: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalancesResponse {
+    pub balances: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SolventResponse {
+    pub solvent: bool,
+    pub shortfall: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RefundResponse {
+    pub refund: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomPairResponse {
+    pub collateral_denoms: Vec<String>,
+    pub counter_offer_denoms: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CreatedAtResponse {
+    pub created_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExampleMessagesResponse {
+    #[cfg(feature = "transferable")]
+    pub transfer: Binary,
+    #[cfg(feature = "executable")]
+    pub execute: Binary,
+    #[cfg(feature = "burnable")]
+    pub burn: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WouldAcceptResponse {
+    pub accepted: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransitionsResponse {
+    pub actions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Trait {
+    pub trait_type: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftInfoResponse {
+    pub name: String,
+    pub description: String,
+    pub attributes: Vec<Trait>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BookKeyResponse {
+    pub key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CurrentCounterOfferResponse {
+    pub counter_offer: Vec<Coin>,
+    pub revision: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RequiredPaymentResponse {
+    pub required: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PaymentProgressResponse {
+    pub paid: Vec<Coin>,
+    pub remaining: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FinalActionResponse {
+    pub action: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NearExpiryResponse {
+    pub near_expiry: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrationHistoryResponse {
+    pub migrations: Vec<MigrationRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferHistoryResponse {
+    pub transfers: Vec<TransferRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ApprovalsResponse {
+    pub approvals: Vec<Approval>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LifePercentResponse {
+    pub remaining: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BuildInfoResponse {
+    pub name: String,
+    pub version: String,
+    pub git_hash: Option<String>,
+}
@@ -1,8 +1,9 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Coin};
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, Binary, Coin, Uint128};
+use cw_storage_plus::{Item, Map};
+use std::fmt;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
@@ -11,6 +12,437 @@ pub struct State {
     pub collateral: Vec<Coin>,
     pub counter_offer: Vec<Coin>,
     pub expires: u64,
+    // Fields below were added after the first release. They must stay `serde(default)` so
+    // state written by older versions still loads after a migration.
+    #[serde(default)]
+    pub option_type: OptionKind,
+    #[serde(default)]
+    pub metadata_uri: Option<String>,
+    #[serde(default)]
+    pub early_exercise_penalty_bps: u16,
+    #[serde(default)]
+    pub penalty_until_height: u64,
+    #[serde(default)]
+    pub pending_creator: Option<Addr>,
+    #[serde(default)]
+    pub created_height: u64,
+    #[serde(default)]
+    pub min_life_blocks: u64,
+    #[serde(default)]
+    pub admin: Option<Addr>,
+    #[serde(default)]
+    pub frozen_denoms: Vec<String>,
+    #[serde(default)]
+    pub burn_fee_bps: u16,
+    #[serde(default)]
+    pub burn_fee_recipient: Option<Addr>,
+    #[serde(default)]
+    pub burn_actual_balance: bool,
+    #[serde(default)]
+    pub creator_callback: Option<Addr>,
+    #[serde(default)]
+    pub alternative_counter_offers: Vec<Vec<Coin>>,
+    #[serde(default)]
+    pub recovery_address: Option<Addr>,
+    #[serde(default)]
+    pub recovery_after_blocks: u64,
+    #[serde(default)]
+    pub topup_extends_expiry: bool,
+    #[serde(default)]
+    pub extension_per_topup_blocks: u64,
+    /// Installments paid towards the counter offer, held until fully paid or refunded
+    #[serde(default)]
+    pub paid_so_far: Vec<Coin>,
+    #[serde(default)]
+    pub distribution_contract: Option<Addr>,
+    #[serde(default)]
+    pub dust_recipient: Option<DustPolicy>,
+    /// Once set by the owner, the option can never be transferred again
+    #[serde(default)]
+    pub soulbound: bool,
+    #[serde(default)]
+    pub warning_window_blocks: u64,
+    #[serde(default)]
+    pub execution_condition: Option<(Addr, Binary)>,
+    #[serde(default)]
+    pub min_refund_threshold: Option<Coin>,
+    #[serde(default)]
+    pub swap_router: Option<Addr>,
+    #[serde(default)]
+    pub desired_proceeds_denom: Option<String>,
+    #[serde(default)]
+    pub min_swap_output: Uint128,
+    #[serde(default)]
+    pub lapse_to_creator: bool,
+    #[serde(default)]
+    pub inclusive_expiry: bool,
+    /// Secondary owner paid `co_owner_split_bps` of the collateral on execute; cleared when the
+    /// option changes hands
+    #[serde(default)]
+    pub co_owner: Option<Addr>,
+    #[serde(default)]
+    pub co_owner_split_bps: u16,
+    #[serde(default)]
+    pub creator_transfer_hook: Option<Addr>,
+    #[serde(default)]
+    pub burn_confirmation_blocks: u64,
+    #[serde(default)]
+    pub two_phase_settlement: bool,
+    #[serde(default)]
+    pub unwrap_on_burn: Option<Addr>,
+    #[serde(default)]
+    pub accepted_denoms: Option<Vec<String>>,
+    #[serde(default)]
+    pub time_oracle: Option<Addr>,
+    #[serde(default)]
+    pub history_len: Option<u32>,
+    #[serde(default)]
+    pub private: bool,
+    #[serde(default)]
+    pub vesting_contract: Option<Addr>,
+    /// Time oracle reading at instantiate, the start of the option's life under `time_oracle`
+    #[serde(default)]
+    pub created_time: Option<u64>,
+    // counter offer limits from instantiate, enforced again when the option is relisted
+    #[serde(default)]
+    pub settlement_denom: Option<String>,
+    #[serde(default)]
+    pub lot_size: Option<Coin>,
+    #[serde(default)]
+    pub max_counter_offer_denoms: Option<u32>,
+    #[serde(default)]
+    pub reject_at_the_money: bool,
+    #[serde(default)]
+    pub oracle: Option<Addr>,
+    #[serde(default)]
+    pub max_mispricing_bps: u16,
+    /// Bumped every time `Relist` replaces the counter offer
+    #[serde(default)]
+    pub counter_offer_revision: u32,
+}
+
+impl State {
+    /// A state holding only the original release's fields, with every later field at the
+    /// default it gets when loaded from old storage. Lets tests built on this crate construct
+    /// a `State` without naming fields that may be added later.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn new_for_test(
+        creator: Addr,
+        owner: Addr,
+        collateral: Vec<Coin>,
+        counter_offer: Vec<Coin>,
+        expires: u64,
+    ) -> Self {
+        State {
+            creator,
+            owner,
+            collateral,
+            counter_offer,
+            expires,
+            option_type: Default::default(),
+            metadata_uri: Default::default(),
+            early_exercise_penalty_bps: Default::default(),
+            penalty_until_height: Default::default(),
+            pending_creator: Default::default(),
+            created_height: Default::default(),
+            min_life_blocks: Default::default(),
+            admin: Default::default(),
+            frozen_denoms: Default::default(),
+            burn_fee_bps: Default::default(),
+            burn_fee_recipient: Default::default(),
+            burn_actual_balance: Default::default(),
+            creator_callback: Default::default(),
+            alternative_counter_offers: Default::default(),
+            recovery_address: Default::default(),
+            recovery_after_blocks: Default::default(),
+            topup_extends_expiry: Default::default(),
+            extension_per_topup_blocks: Default::default(),
+            paid_so_far: Default::default(),
+            distribution_contract: Default::default(),
+            dust_recipient: Default::default(),
+            soulbound: Default::default(),
+            warning_window_blocks: Default::default(),
+            execution_condition: Default::default(),
+            min_refund_threshold: Default::default(),
+            swap_router: Default::default(),
+            desired_proceeds_denom: Default::default(),
+            min_swap_output: Default::default(),
+            lapse_to_creator: Default::default(),
+            inclusive_expiry: Default::default(),
+            co_owner: Default::default(),
+            co_owner_split_bps: Default::default(),
+            creator_transfer_hook: Default::default(),
+            burn_confirmation_blocks: Default::default(),
+            two_phase_settlement: Default::default(),
+            unwrap_on_burn: Default::default(),
+            accepted_denoms: Default::default(),
+            time_oracle: Default::default(),
+            history_len: Default::default(),
+            private: Default::default(),
+            vesting_contract: Default::default(),
+            created_time: Default::default(),
+            settlement_denom: Default::default(),
+            lot_size: Default::default(),
+            max_counter_offer_denoms: Default::default(),
+            reject_at_the_money: Default::default(),
+            oracle: Default::default(),
+            max_mispricing_bps: Default::default(),
+            counter_offer_revision: Default::default(),
+        }
+    }
+
+    /// First block at which the option is expired: `expires` itself, or the block after it
+    /// with `inclusive_expiry`
+    pub fn expired_at(&self) -> u64 {
+        if self.inclusive_expiry {
+            self.expires.saturating_add(1)
+        } else {
+            self.expires
+        }
+    }
+
+    pub fn is_expired(&self, height: u64) -> bool {
+        height >= self.expired_at()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomAlias {
+    pub alias: String,
+    pub canonical: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomDisplay {
+    pub denom: String,
+    pub display: String,
+}
+
+/// Snapshot of the terms the option was instantiated with. Never updated afterwards.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OriginalTerms {
+    pub creator: Addr,
+    pub collateral: Vec<Coin>,
+    pub counter_offer: Vec<Coin>,
+    pub expires: u64,
+    pub created_height: u64,
+}
+
+/// Party that receives the remainder when a fee or penalty split does not divide evenly.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DustPolicy {
+    ToCreator,
+    ToOwner,
+    ToFeeRecipient,
+}
+
+/// Informational tag describing what kind of option this contract represents.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionKind {
+    Call,
+    Put,
+    Other(String),
+}
+
+// `#[default]` on enum variants needs a newer toolchain than CI pins
+#[allow(clippy::derivable_impls)]
+impl Default for OptionKind {
+    fn default() -> Self {
+        OptionKind::Call
+    }
+}
+
+impl fmt::Display for OptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionKind::Call => write!(f, "call"),
+            OptionKind::Put => write!(f, "put"),
+            OptionKind::Other(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+/// Fee settings fixed at instantiate. Kept apart from `State` so fees can still be
+/// withdrawn once the option has settled.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeConfig {
+    pub admin: Addr,
+    pub execute_fee_bps: u16,
+}
+
+/// Counter offer deposited by a prospective buyer ahead of execute
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Escrow {
+    pub depositor: Addr,
+    pub funds: Vec<Coin>,
+}
+
+/// Proceeds handed to the distribution contract, kept until its reply arrives so they can be
+/// paid out directly if it fails
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingDistribution {
+    pub beneficiary: Addr,
+    pub funds: Vec<Coin>,
+}
+
+/// One `migrate` call, as recorded in `MIGRATIONS`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrationRecord {
+    pub from_version: String,
+    pub to_version: String,
+    pub height: u64,
+}
+
+/// One `Transfer`, as recorded in `TRANSFER_HISTORY`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferRecord {
+    pub from: Addr,
+    pub to: Addr,
+    pub height: u64,
+}
+
+/// Operator allowed to transfer and execute on the owner's behalf, until `expires` if set
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Approval {
+    pub operator: Addr,
+    pub expires: Option<u64>,
+}
+
+/// Last time reported by the time oracle and the block it was read at
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OracleTime {
+    pub height: u64,
+    pub time: u64,
 }
 
 pub const STATE: Item<State> = Item::new("state");
+pub const ORIGINAL_TERMS: Item<OriginalTerms> = Item::new("original_terms");
+/// alias denom -> canonical denom
+pub const DENOM_ALIASES: Map<&str, String> = Map::new("denom_aliases");
+/// raw denom -> human readable name, only used when presenting the config
+pub const DENOM_DISPLAY: Map<&str, String> = Map::new("denom_display");
+pub const FEE_CONFIG: Item<FeeConfig> = Item::new("fee_config");
+pub const ESCROW: Item<Escrow> = Item::new("escrow");
+pub const PENDING_DISTRIBUTION: Item<PendingDistribution> = Item::new("pending_distribution");
+/// Collateral sent to the unwrap contract on burn, kept until its reply arrives so it can be
+/// returned to the creator directly if unwrapping fails
+pub const PENDING_UNWRAP: Item<PendingDistribution> = Item::new("pending_unwrap");
+/// Proceeds sent to the vesting contract, kept until its reply arrives so they can be paid
+/// out directly if it fails
+pub const PENDING_VESTING: Item<PendingDistribution> = Item::new("pending_vesting");
+/// Collateral owed to the owner in a two-phase settlement, released once the creator is paid
+pub const PENDING_COLLATERAL: Item<PendingDistribution> = Item::new("pending_collateral");
+/// Execute fees held by the contract until the fee admin withdraws them
+pub const FEES_ACCRUED: Item<Vec<Coin>> = Item::new("fees_accrued");
+/// operator -> approval granted by the current owner, cleared on transfer
+pub const APPROVALS: Map<&Addr, Approval> = Map::new("approvals");
+/// Bonus coins paid to the owner on execute, by who funded them
+pub const EXECUTOR_REWARDS: Map<&Addr, Vec<Coin>> = Map::new("executor_rewards");
+/// Installments credited to `paid_so_far`, by who paid them, so refunds go back to the payer
+pub const INSTALLMENTS: Map<&Addr, Vec<Coin>> = Map::new("installments");
+/// migration index -> record, appended by every `migrate`
+pub const MIGRATIONS: Map<u64, MigrationRecord> = Map::new("migrations");
+pub const ORACLE_TIME: Item<OracleTime> = Item::new("oracle_time");
+/// Set at instantiate for private options; outlives `STATE` so settled options stay redacted
+pub const PRIVATE: Item<bool> = Item::new("private");
+/// The last `history_len` transfers, oldest first
+pub const TRANSFER_HISTORY: Item<Vec<TransferRecord>> = Item::new("transfer_history");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{coins, from_slice};
+
+    #[test]
+    fn new_for_test_matches_original_state() {
+        let json = br#"{
+            "creator": "creator",
+            "owner": "owner",
+            "collateral": [{"denom": "BTC", "amount": "1"}],
+            "counter_offer": [{"denom": "ETH", "amount": "40"}],
+            "expires": 100000
+        }"#;
+        let state = State::new_for_test(
+            Addr::unchecked("creator"),
+            Addr::unchecked("owner"),
+            coins(1, "BTC"),
+            coins(40, "ETH"),
+            100_000,
+        );
+
+        assert_eq!(from_slice::<State>(json).unwrap(), state);
+        assert_eq!(OptionKind::Call, state.option_type);
+        assert!(state.paid_so_far.is_empty());
+        assert!(!state.is_expired(99_999));
+    }
+
+    #[test]
+    fn deserialize_original_state() {
+        // State as written by the first release of the contract
+        let json = br#"{
+            "creator": "creator",
+            "owner": "owner",
+            "collateral": [{"denom": "BTC", "amount": "1"}],
+            "counter_offer": [{"denom": "ETH", "amount": "40"}],
+            "expires": 100000
+        }"#;
+        let state: State = from_slice(json).unwrap();
+
+        assert_eq!("creator", state.creator);
+        assert_eq!("owner", state.owner);
+        assert_eq!(coins(1, "BTC"), state.collateral);
+        assert_eq!(coins(40, "ETH"), state.counter_offer);
+        assert_eq!(100_000, state.expires);
+        assert_eq!(OptionKind::Call, state.option_type);
+        assert_eq!(None, state.metadata_uri);
+        assert_eq!(0, state.early_exercise_penalty_bps);
+        assert_eq!(0, state.penalty_until_height);
+        assert_eq!(None, state.pending_creator);
+        assert_eq!(0, state.created_height);
+        assert_eq!(0, state.min_life_blocks);
+        assert_eq!(None, state.admin);
+        assert!(state.frozen_denoms.is_empty());
+        assert_eq!(0, state.burn_fee_bps);
+        assert_eq!(None, state.burn_fee_recipient);
+        assert!(!state.burn_actual_balance);
+        assert_eq!(None, state.creator_callback);
+        assert!(state.alternative_counter_offers.is_empty());
+        assert_eq!(None, state.recovery_address);
+        assert_eq!(0, state.recovery_after_blocks);
+        assert!(!state.topup_extends_expiry);
+        assert_eq!(0, state.extension_per_topup_blocks);
+        assert!(state.paid_so_far.is_empty());
+        assert_eq!(None, state.distribution_contract);
+        assert_eq!(None, state.dust_recipient);
+        assert!(!state.soulbound);
+        assert_eq!(0, state.warning_window_blocks);
+        assert_eq!(None, state.execution_condition);
+        assert_eq!(None, state.min_refund_threshold);
+        assert_eq!(None, state.swap_router);
+        assert_eq!(None, state.desired_proceeds_denom);
+        assert_eq!(Uint128::zero(), state.min_swap_output);
+        assert!(!state.lapse_to_creator);
+        assert!(!state.inclusive_expiry);
+        assert_eq!(None, state.co_owner);
+        assert_eq!(0, state.co_owner_split_bps);
+        assert_eq!(None, state.creator_transfer_hook);
+        assert_eq!(0, state.burn_confirmation_blocks);
+        assert!(!state.two_phase_settlement);
+        assert_eq!(None, state.unwrap_on_burn);
+        assert_eq!(None, state.accepted_denoms);
+        assert_eq!(None, state.time_oracle);
+        assert_eq!(None, state.history_len);
+        assert!(!state.private);
+        assert_eq!(None, state.vesting_contract);
+        assert_eq!(None, state.created_time);
+        assert_eq!(None, state.settlement_denom);
+        assert_eq!(None, state.lot_size);
+        assert_eq!(None, state.max_counter_offer_denoms);
+        assert!(!state.reject_at_the_money);
+        assert_eq!(None, state.oracle);
+        assert_eq!(0, state.max_mispricing_bps);
+        assert_eq!(0, state.counter_offer_revision);
+    }
+}
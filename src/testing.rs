@@ -0,0 +1,12 @@
+//! Helpers for tests of this contract and of contracts built on top of it.
+
+use cosmwasm_std::testing::mock_env;
+use cosmwasm_std::{Env, Timestamp};
+
+/// A mock `Env` with both the block height and the block time pinned.
+pub fn env_at(height: u64, time_secs: u64) -> Env {
+    let mut env = mock_env();
+    env.block.height = height;
+    env.block.time = Timestamp::from_seconds(time_secs);
+    env
+}